@@ -0,0 +1,40 @@
+use criterion::*;
+
+use icfp2019::bench::*;
+use icfp2019::{Angle, Pos, PosAngle, PosDiff, System};
+
+fn manipulator_benchmark(c: &mut Criterion) {
+    c.bench_function("plot", |b| {
+        b.iter(|| {
+            for dx in -3..=3 {
+                for dy in -3..=3 {
+                    black_box(plot(PosDiff::new(dx, dy)));
+                }
+            }
+        })
+    });
+    for size in SIZES.iter() {
+        let system = System::from_task(task(size));
+        // Whether the bot could mark with each of its manipulators, facing east, from every cell.
+        c.bench_function(&format!("can_mark/{}", size), move |b| {
+            let map = system.map();
+            let manipulators = system.bots()[0].manipulators();
+            b.iter(|| {
+                let mut count = 0;
+                for y in 0..map.max_y {
+                    for x in 0..map.max_x {
+                        let pos_angle = PosAngle::new(Pos::new(x, y), Angle::A0);
+                        count += manipulators
+                            .iter()
+                            .filter(|manipulator| manipulator.can_mark(pos_angle, map))
+                            .count();
+                    }
+                }
+                count
+            })
+        });
+    }
+}
+
+criterion_group!(benches, manipulator_benchmark);
+criterion_main!(benches);
@@ -0,0 +1,21 @@
+use criterion::*;
+
+use icfp2019::bench::*;
+use icfp2019::{Map, Task};
+
+fn map_benchmark(c: &mut Criterion) {
+    for size in SIZES.iter() {
+        let task = task(size);
+        // Generating the task takes much longer than parsing it.
+        let desc = task.to_string();
+        c.bench_function(&format!("map_new/{}", size), move |b| {
+            b.iter_with_setup(|| Task::parse(0, &desc).unwrap(), Map::new)
+        });
+        c.bench_function(&format!("fill_tour/{}", size), move |b| {
+            b.iter(|| fill_tour(&task))
+        });
+    }
+}
+
+criterion_group!(benches, map_benchmark);
+criterion_main!(benches);
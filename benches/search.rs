@@ -0,0 +1,20 @@
+use criterion::*;
+
+use icfp2019::bench::*;
+
+fn search_benchmark(c: &mut Criterion) {
+    for size in SIZES.iter() {
+        let system = half_painted(task(size));
+        c.bench_function(&format!("find_mark_move/{}", size), move |b| {
+            b.iter(|| find_mark_move(&system))
+        });
+        let system = half_painted(task(size));
+        let goal = furthest_cell(&system);
+        c.bench_function(&format!("find_move_to/{}", size), move |b| {
+            b.iter(|| find_move_to(&system, goal))
+        });
+    }
+}
+
+criterion_group!(benches, search_benchmark);
+criterion_main!(benches);
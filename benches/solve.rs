@@ -0,0 +1,24 @@
+use criterion::*;
+
+use icfp2019::bench::*;
+use icfp2019::{System, Task};
+
+fn solve_benchmark(c: &mut Criterion) {
+    for size in SIZES.iter() {
+        let desc = task(size).to_string();
+        c.bench_function(&format!("solve/{}", size), move |b| {
+            b.iter_with_setup(
+                || System::from_task(Task::parse(0, &desc).unwrap()),
+                |mut system| system.solve().unwrap(),
+            )
+        });
+    }
+}
+
+criterion_group! {
+    name = benches;
+    // A full solve of the large map takes seconds.
+    config = Criterion::default().sample_size(10);
+    targets = solve_benchmark
+}
+criterion_main!(benches);
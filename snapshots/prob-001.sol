@@ -0,0 +1 @@
+WDDDDDDAAAAAQ
\ No newline at end of file
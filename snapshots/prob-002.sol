@@ -0,0 +1 @@
+WWWWWWWWWWWWWWWWWDDDDDAAAAACSSSSSSSSSSSSSSSSDB(1,2)DDDDDDDDDDDDDDDESFAAAAAAAAEWWWWWWWWWSQDSSDDWWDWDSSDSSSDDDWAAAWWWWAAA#SSSSSSSSSSSSDDDDDDDSDDDDB(1,2)DDDSSDDDWWWAWQWWWAAQAAASAAAAAAAAWEDDDDDSDDSEWDDDDDWWAAAWWWWW
\ No newline at end of file
@@ -0,0 +1 @@
+SSSSSSSSSSSSSDDDDDDDDDDDDWWWWWWWWWWWWAAAAAAAAAAAAACWWWWWWWWWWWWWAAAAAAAAAAAASSSSSSSSSSSSSDDDDDDDDDDDDCFSASASSSDSAAAAAAWWDWWASSDDSDDB(1,2)AAWWWDDDWWWAAAWWDDDDSSSDDDDDDDDDDDDDDWWQWWWWWWWAAAAAAASSQSSSSSSSSSSASSSSSDDDDDWWW#WWDDWWWWWWWWWWWWDDDDDDDDDDDDB(1,2)SSSSSSSSSSSSSSSSSSSSSSSSSSSAAAAAAAAAAAAAAAAAAAAAAAAWWQWWWWWWWWAASDWWWWAWWWWWWWWWWWWWWAFDDDDDDDSSAAAAADDDDDDSSSDDDSSSSSSSAAWQADDDDDWWE#SESSSSSSAESQSSSSSAAAAAAAAAAAAADDDWWWWWWAAWDDWWWWWWAAAWWWWDDDWWQWWWDDDDDDDDDDDDDDDWWSSSSSSDSSSSSSSSSESDDDDDQDWWW
\ No newline at end of file
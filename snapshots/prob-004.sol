@@ -0,0 +1 @@
+WAAAAWWWAAASAASAAAAAAASSSAAAAAAAAASSACSSSDDDDDDDDDDDDDDDDDB(1,2)SSSSSSSSSSAAB(1,-2)AAAAWWQWWWWWAAQAAAAAAAASSQSSSSSSSSSSDDQDDDDDDDDDDDDDDDDDDDDDDDDDDDDDDDDDDDWWQWWWWWWWWWWWWWWAAAQFAAASQSSSSSDDWWWWWDWWWWWASSDSSDWWWWWWWAAAASASAAAAWAAAEWWWWWWWAAAAAAAAAAAAASSSSSSDQSSDDSSSDSSSSSSSSSSSSSSSSAWWDDSSDDDDWWWWWWSSSSSSSSSSASAAAAAAADDDDDDDWWWWAWWWWWWWWWWWWWWWWDWWWW#WWWWWWWWWWWWWWWWWWWWDDDDDDDB(1,2)FSDDDDDDDDDDDDDSSDDDSASAAAAAWAAAAAAAWDDDDDDDDDDWWDDAAQWASSSSSSSSSQSQSSAEAAAAAAAAAASSSSSAAAAASSQSSSDDQDDDDWWAWWWWAAQAAWWWWWWAAAQAAAAAAWWWWAAAAAAAAASSQSSSSSSDDQDDDDSSSSESDDDDDDQDWWWSSSSSSSESSSSSDDQDDWDDDDDWDWWSSSDSSSSEESAAAAAAAWWSSAAAASSAAAAAAADDWWWWWWWWWWWWWWWAWWWWWWDWWWWWWWEW
\ No newline at end of file
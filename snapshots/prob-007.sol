@@ -0,0 +1 @@
+SSSSSSSSSSSSSSSSSSSSSSSSSSSSSSSSSSSAAASAAAAAAAAAASSAAAAAB(1,2)AAWWWDDDDDDDDDDDDDDDDWDDWQWWWWWWWWWWWWWWWWWWWWWWWWWWWWWWWWWWWWWWWWWWWWWWWAAQAAAAAAAAAAAAAAASSQSSSSSSSSSSSSSSSSSSSSSSSSSSSSSSSSSSSSSSSSSSSDDQDDDDDDDDDDDWWQWWWWWWWWWWWWWWWWWWWWWWWWWAWAWWWWWWWWWWWWWAAQAAAAASSQSSSSSSSSSSSSSSSSSSSSSSSSSSSSSSSSSSSDDQDDDWWQWWWWWWWWWWWWWWWWWWWWWWWWWWWWWWWDDDEDWWWASSSSSDSSSDDDDDDDDDWWQWWWWWWWWWWWASSSSSSSSSSSSSSESSDSESSSSSSSSSSSSSSSSSSSSSSSSSSSSSSSSSSSSSSSSAEAAAAAAAAAAAAAAAAAAAAAWEWDDDDDDDDDWDEDDDDDDDDDWAASAAAAAAEAAAAAAAAAAAEWWWWWWWWWWWWWWWWWWWWWWWWWWWDEDDDDDDDDDDDDDDDDDDDWWWWWWWWW
\ No newline at end of file
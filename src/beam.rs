@@ -0,0 +1,473 @@
+use std::cmp::Reverse;
+use std::time::{Duration, Instant};
+
+use crate::grid::*;
+use crate::prelude::*;
+use crate::system::*;
+use crate::task::*;
+
+#[derive(Debug, Clone, Copy)]
+pub struct BeamOptions {
+    pub width: usize,
+    pub time_budget: Duration,
+}
+
+impl Default for BeamOptions {
+    fn default() -> BeamOptions {
+        BeamOptions {
+            width: 8,
+            time_budget: Duration::from_secs(10),
+        }
+    }
+}
+
+// How many of the nearest clusters of unpainted cells are tried as destinations.
+const FRONTIER_CLUSTERS: usize = 3;
+
+#[derive(Debug, Clone)]
+enum MacroMove {
+    // One step of the greedy solver.
+    Greedy,
+    // Walk (and turn) until the bot paints a cell of a frontier cluster.
+    Paint(Vec<Action>),
+    Turn(Action),
+    // Fetch the booster and use it right away.
+    UseBooster(Pos, BoosterKind),
+}
+
+// One action of a macro-move. Those which depend on where the bot gets to are worked out when
+// it gets there.
+#[derive(Debug, Clone)]
+enum Step {
+    Act(Action),
+    Extend,
+    ToMysterious,
+}
+
+// A state of the search: the system, all of whose bots are at the same turn, and the steps each
+// bot has left of its macro-move.
+#[derive(Clone)]
+struct State {
+    system: System,
+    steps: Vec<VecDeque<Step>>,
+}
+
+// Lower is better: more painted cells, then closer to unpainted cells.
+type Rank = (Reverse<usize>, usize);
+
+const BEAM_BOOSTERS: [BoosterKind; 3] = [
+    BoosterKind::ExtendManipulator,
+    BoosterKind::FastWheels,
+    BoosterKind::Cloning,
+];
+
+pub fn solve(mut system: System, options: &BeamOptions) -> Result<Solution> {
+    // The time limit of the run cuts the search short, but the greedy solutions are what is left
+    // then, so they are finished regardless.
+    let mut deadline = Instant::now() + options.time_budget;
+    if let Some(limit) = system.deadline.take() {
+        deadline = deadline.min(limit);
+    }
+
+    // The greedy solution bounds the search, and is kept if the search can not beat it. The
+    // search starts from the clone plan it picked.
+    let cloned = system.plan_cloning()?;
+    system.clone_plan = cloned.plan;
+    let mut best = cloned.solved;
+    debug!("beam: greedy solution: {}", best.turns());
+
+    // Every bot takes one action per turn, in the same order as the replay of the solution, so
+    // the states of a layer are all at the same turn.
+    let mut layer = vec![State {
+        steps: vec![VecDeque::new(); system.bots.len()],
+        system,
+    }];
+    while !layer.is_empty() && Instant::now() < deadline {
+        if layer[0].system.turns() + 1 >= best.turns() {
+            // No state can improve on the best any more.
+            layer.clear();
+            break;
+        }
+        let mut next_layer = vec![];
+        for state in select(layer, options.width) {
+            for mut next in expand(state) {
+                if take_turn(&mut next).is_none() {
+                    continue;
+                }
+                if next.system.is_solved() {
+                    if next.system.turns() < best.turns() {
+                        debug!("beam: found a solution: {}", next.system.turns());
+                        best = next.system;
+                    }
+                } else {
+                    next_layer.push(next);
+                }
+            }
+        }
+        layer = next_layer;
+    }
+
+    // Out of time: finish the most promising pending state greedily.
+    if let Some(mut state) = select(layer, 1).into_iter().next() {
+        state.system.solve()?;
+        if state.system.turns() < best.turns() {
+            best = state.system;
+        }
+    }
+    Ok(best.solution())
+}
+
+// Keeps the best `width` distinct states.
+fn select(states: Vec<State>, width: usize) -> Vec<State> {
+    let mut ranked = states
+        .into_iter()
+        .map(|state| (rank(&state.system), state))
+        .collect::<Vec<_>>();
+    ranked.sort_by_key(|(rank, _)| *rank);
+
+    let mut seen = HashSet::new();
+    ranked
+        .into_iter()
+        .map(|(_, state)| state)
+        .filter(|state| {
+            seen.insert((
+                state.system.map.empty_cell_count,
+                state
+                    .system
+                    .bots
+                    .iter()
+                    .map(|bot| bot.pos_angle)
+                    .collect::<Vec<_>>(),
+            ))
+        })
+        .take(width)
+        .collect()
+}
+
+fn rank(system: &System) -> Rank {
+    let painted = system.map.max_x as usize * system.map.max_y as usize
+        - system.map.walls.count()
+        - system.map.empty_cell_count;
+    (
+        Reverse(painted),
+        (0..system.bots.len())
+            .map(|bot| distance_to_unpainted(system, bot))
+            .fold(0, usize::saturating_add),
+    )
+}
+
+// The states in which the first bot without steps left starts each of its macro-moves. Other
+// bots without steps take a step of the greedy solver, or wait.
+fn expand(mut state: State) -> Vec<State> {
+    let idle = (0..state.steps.len())
+        .filter(|bot| state.steps[*bot].is_empty())
+        .collect::<Vec<_>>();
+    for bot in idle.iter().skip(1) {
+        state.steps[*bot] = macro_steps(&state.system, *bot, &MacroMove::Greedy)
+            .unwrap_or_else(|| vec![Step::Act(Action::DoNothing)].into());
+    }
+    let bot = match idle.first() {
+        Some(bot) => *bot,
+        None => return vec![state],
+    };
+    macro_moves(&state.system, bot)
+        .iter()
+        .filter_map(|macro_move| macro_steps(&state.system, bot, macro_move))
+        .map(|steps| {
+            let mut next = state.clone();
+            next.steps[bot] = steps;
+            next
+        })
+        .collect()
+}
+
+fn distance_to_unpainted(system: &System, bot: usize) -> usize {
+    let map = &system.map;
+    let start = system.bots[bot].pos_angle.pos;
+    let mut visited = BitGrid::new(map.max_x, map.max_y);
+    visited.insert(start);
+    let mut q = VecDeque::new();
+    q.push_back((start, 0));
+    while let Some((pos, len)) = q.pop_front() {
+        if map.is_empty(pos) {
+            return len;
+        }
+        for next in visited.neighbours(pos) {
+            if map.is_free(next) && visited.insert(next) {
+                q.push_back((next, len + 1));
+            }
+        }
+    }
+    usize::MAX
+}
+
+fn macro_moves(system: &System, bot: usize) -> Vec<MacroMove> {
+    let mut moves = vec![MacroMove::Greedy];
+    moves.extend(frontier_moves(system, bot));
+    moves.push(MacroMove::Turn(Action::TurnClockWise));
+    moves.push(MacroMove::Turn(Action::TurnCounterClockWise));
+
+    for kind in &BEAM_BOOSTERS {
+        if *kind == BoosterKind::Cloning && system.mysterious_pos.count() == 0 {
+            continue;
+        }
+        let path = find_path(system, bot, false, |pos_angle| {
+            system.booster_pos.get(pos_angle.pos) == Some(&Some(*kind))
+                && !system.reservations.is_reserved(pos_angle.pos)
+        });
+        if let Some((pos_angle, _)) = path {
+            moves.push(MacroMove::UseBooster(pos_angle.pos, *kind));
+        }
+    }
+    moves
+}
+
+// Label each connected region of unpainted cells.
+fn label_clusters(map: &Map) -> Grid<usize> {
+    let mut labels = Grid::new(map.max_x, map.max_y, 0);
+    let mut next_label = 1;
+    for y in 0..map.max_y {
+        for x in 0..map.max_x {
+            let start = Pos::new(x, y);
+            if !map.is_empty(start) || labels[start] != 0 {
+                continue;
+            }
+            labels[start] = next_label;
+            let mut q = VecDeque::new();
+            q.push_back(start);
+            while let Some(pos) = q.pop_front() {
+                for next in labels.neighbours(pos).collect::<Vec<_>>() {
+                    if map.is_empty(next) && labels[next] == 0 {
+                        labels[next] = next_label;
+                        q.push_back(next);
+                    }
+                }
+            }
+            next_label += 1;
+        }
+    }
+    labels
+}
+
+fn frontier_moves(system: &System, bot: usize) -> Vec<MacroMove> {
+    let labels = label_clusters(&system.map);
+    let manipulators = &system.bots[bot].manipulators;
+    let mut found = HashSet::new();
+    let mut moves = vec![];
+    while moves.len() < FRONTIER_CLUSTERS {
+        let path = find_path(system, bot, true, |pos_angle| {
+            manipulators.iter().any(|manipulator| {
+                manipulator.can_mark(pos_angle, &system.map)
+                    && !found.contains(&labels[pos_angle + manipulator.posdiff])
+            })
+        });
+        match path {
+            Some((pos_angle, actions)) => {
+                for manipulator in manipulators {
+                    if manipulator.can_mark(pos_angle, &system.map) {
+                        found.insert(labels[pos_angle + manipulator.posdiff]);
+                    }
+                }
+                moves.push(MacroMove::Paint(actions));
+            }
+            None => break,
+        }
+    }
+    moves
+}
+
+// BFS from the bot to the nearest pose satisfying the predicate. Uses the same move model as
+// the greedy searches, without drilling.
+fn find_path<P>(
+    system: &System,
+    bot: usize,
+    with_turns: bool,
+    mut predicate: P,
+) -> Option<(PosAngle, Vec<Action>)>
+where
+    P: FnMut(PosAngle) -> bool,
+{
+    use Action::*;
+    let bot = &system.bots[bot];
+    let actions: &[Action] = if with_turns {
+        &[
+            MoveUp,
+            MoveDown,
+            MoveLeft,
+            MoveRight,
+            TurnClockWise,
+            TurnCounterClockWise,
+        ]
+    } else {
+        &[MoveUp, MoveDown, MoveLeft, MoveRight]
+    };
+
+    // (pos_angle, len, parent, action)
+    let mut nodes: Vec<(PosAngle, usize, usize, Action)> = vec![(bot.pos_angle, 0, 0, DoNothing)];
+    let mut visited = PosAngleSet::new(&system.map);
+    visited.insert(bot.pos_angle, bot.fast_wheel_timer > 0, false);
+    let mut q = VecDeque::new();
+    q.push_back(0);
+
+    while let Some(current) = q.pop_front() {
+        let (pos_angle, len, ..) = nodes[current];
+        let can_use_fast_wheel = bot.fast_wheel_timer > len;
+        for action in actions {
+            let next = match system.next_move(pos_angle, *action, false, can_use_fast_wheel) {
+                Some(next_move) => next_move.pos_angle,
+                None => continue,
+            };
+            let next_can_use_fast_wheel = bot.fast_wheel_timer > len + 1;
+            if visited.contains(next, next_can_use_fast_wheel, false) {
+                continue;
+            }
+            visited.insert(next, next_can_use_fast_wheel, false);
+            nodes.push((next, len + 1, current, *action));
+            let index = nodes.len() - 1;
+            if predicate(next) {
+                let mut path = vec![];
+                let mut i = index;
+                while i != 0 {
+                    path.push(nodes[i].3);
+                    i = nodes[i].2;
+                }
+                path.reverse();
+                return Some((next, path));
+            }
+            q.push_back(index);
+        }
+    }
+    None
+}
+
+// The steps of the macro-move, from the state at which the bot starts it. None if it is not
+// possible.
+fn macro_steps(system: &System, bot: usize, macro_move: &MacroMove) -> Option<VecDeque<Step>> {
+    let mut steps = VecDeque::new();
+    match macro_move {
+        MacroMove::Greedy => {
+            let action = system
+                .find_mark_move(&system.bots[bot])
+                .ok()?
+                .first_action();
+            steps.push_back(Step::Act(action));
+        }
+        MacroMove::Paint(actions) => {
+            steps.extend(actions.iter().map(|action| Step::Act(*action)));
+        }
+        MacroMove::Turn(action) => {
+            steps.push_back(Step::Act(*action));
+        }
+        MacroMove::UseBooster(pos, kind) => {
+            let (_, actions) = find_path(system, bot, false, |pos_angle| pos_angle.pos == *pos)?;
+            if actions.is_empty() {
+                // Standing on it does not pick it up.
+                return None;
+            }
+            steps.extend(actions.into_iter().map(Step::Act));
+            match kind {
+                BoosterKind::ExtendManipulator => steps.push_back(Step::Extend),
+                BoosterKind::FastWheels => steps.push_back(Step::Act(Action::AttachFastWheels)),
+                BoosterKind::Cloning => {
+                    steps.push_back(Step::ToMysterious);
+                    steps.push_back(Step::Act(Action::Cloning));
+                }
+                _ => unreachable!(),
+            }
+        }
+    }
+    Some(steps)
+}
+
+// The next action of the bot's macro-move.
+fn next_action(system: &System, bot: usize, steps: &mut VecDeque<Step>) -> Option<Action> {
+    loop {
+        match steps.pop_front()? {
+            Step::Act(action) => return Some(action),
+            Step::Extend => {
+                let posdiff = system.bots[bot].find_extend_manipulator_position();
+                return Some(Action::ExtendManipulator(posdiff));
+            }
+            Step::ToMysterious => {
+                let (_, actions) = find_path(system, bot, false, |pos_angle| {
+                    system.mysterious_pos.contains(pos_angle.pos)
+                })?;
+                for action in actions.into_iter().rev() {
+                    steps.push_front(Step::Act(action));
+                }
+            }
+        }
+    }
+}
+
+// Every bot which exists at the start of the turn takes its next action, as in System::solve.
+// None if an action turns out to be illegal, as another bot got first to the booster it was
+// going for, in which case the state is left half-way and should be dropped.
+fn take_turn(state: &mut State) -> Option<()> {
+    for bot in 0..state.system.bots.len() {
+        if state.system.is_solved() {
+            break;
+        }
+        let action = next_action(&state.system, bot, &mut state.steps[bot])?;
+        state.system.check_action(bot, action).ok()?;
+        state.system.step(bot, action);
+    }
+    state.steps.resize(state.system.bots.len(), VecDeque::new());
+    Some(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn beam_test() -> Result<()> {
+        let options = BeamOptions {
+            width: 4,
+            time_budget: Duration::from_secs(5),
+        };
+        for id in 1..=2 {
+            let greedy = System::new(id)?.plan_cloning()?.solved;
+            let solution = solve(System::new(id)?, &options)?;
+            assert!(solution.score <= greedy.solution().score);
+
+            // The solution is legal and paints the whole map.
+            let map = Map::new(Task::read_with_id(id)?);
+            let replay = crate::replay::replay(map, &solution.solution, &[])?;
+            assert_eq!(replay.turns, solution.score);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn multi_bot_beam_test() -> Result<()> {
+        // The bots of a state act in the same turns as in the replay.
+        let options = BeamOptions {
+            width: 4,
+            time_budget: Duration::from_secs(1),
+        };
+        for seed in 0..3 {
+            let generate = crate::generate::GenerateOptions {
+                boosters: 3,
+                seed,
+                ..crate::generate::GenerateOptions::default()
+            };
+            let map = Map::new(crate::generate::generate(0, &generate)?);
+            let solution = solve(System::from_map(map.clone()), &options)?;
+            let replay = crate::replay::replay(map, &solution.solution, &[])?;
+            assert!(replay.bots > 1);
+            assert_eq!(replay.turns, solution.score);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn expired_deadline_test() -> Result<()> {
+        let greedy = System::new(1)?.plan_cloning()?.solved;
+        let mut system = System::new(1)?;
+        system.set_deadline(Instant::now());
+        let solution = solve(system, &BeamOptions::default())?;
+        assert_eq!(solution.solution, greedy.solution().solution);
+        Ok(())
+    }
+}
@@ -0,0 +1,93 @@
+use crate::generate::*;
+use crate::grid::*;
+use crate::system::*;
+use crate::task::*;
+use crate::visibility::*;
+
+// Inputs of the criterion benches in benches/, which reach into the solver. Built with the bench
+// feature, and for tests.
+
+pub const SIZES: [&str; 3] = ["small", "medium", "large"];
+
+// A generated task of the given size, the same on every run.
+pub fn task(size: &str) -> Task {
+    let size = match size {
+        "small" => 20,
+        "medium" => 40,
+        "large" => 80,
+        _ => panic!("unknown size: {}", size),
+    };
+    let options = GenerateOptions {
+        size,
+        erosion: (size * size / 10) as usize,
+        obstacles: (size / 4) as usize,
+        boosters: (size / 40 + 1) as usize,
+        seed: 0,
+    };
+    generate(0, &options).unwrap()
+}
+
+pub fn fill_tour(task: &Task) -> BitGrid {
+    Map::fill_tour(task.map(), task.max_x(), task.max_y())
+}
+
+fn free_cells(map: &Map) -> Vec<Pos> {
+    (0..map.max_y)
+        .flat_map(|y| (0..map.max_x).map(move |x| Pos::new(x, y)))
+        .filter(|pos| !map.is_wall(*pos))
+        .collect()
+}
+
+// A system where the cells around the bot are painted already, so that find_mark_move has to
+// look beyond them as it does in the middle of a run.
+pub fn half_painted(task: Task) -> System {
+    let mut system = System::from_task(task);
+    let start = system.bots[0].pos_angle.pos;
+    let distance = |pos: Pos| (pos.x - start.x).abs() + (pos.y - start.y).abs();
+    let free = free_cells(&system.map);
+    let max_distance = free.iter().map(|pos| distance(*pos)).max().unwrap_or(0);
+    for pos in free {
+        if distance(pos) < max_distance / 2 {
+            system.map.mark_pos(pos);
+        }
+    }
+    system.gain_maps = GainMaps::new(&system.bots, &system.map);
+    system
+}
+
+// The free cell furthest from the bot, as the goal of find_move_to.
+pub fn furthest_cell(system: &System) -> Pos {
+    let start = system.bots[0].pos_angle.pos;
+    free_cells(&system.map)
+        .into_iter()
+        .max_by_key(|pos| (pos.x - start.x).abs() + (pos.y - start.y).abs())
+        .unwrap()
+}
+
+pub fn find_mark_move(system: &System) -> usize {
+    system.find_mark_move(&system.bots[0]).unwrap().len
+}
+
+pub fn find_move_to(system: &System, goal: Pos) -> usize {
+    system
+        .find_move_to(&system.bots[0], |pos| pos == goal)
+        .unwrap()
+        .len
+}
+
+pub fn plot(posdiff: PosDiff) -> Vec<PosDiff> {
+    Manipulator::plot(posdiff)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn search_test() {
+        let system = half_painted(task("small"));
+        assert!(find_mark_move(&system) > 0);
+        let goal = furthest_cell(&system);
+        assert!(find_move_to(&system, goal) > 0);
+    }
+}
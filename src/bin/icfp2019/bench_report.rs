@@ -0,0 +1,167 @@
+use std::collections::BTreeMap;
+
+use crate::prelude::*;
+
+// The report comparing the last results of the criterion benches in benches/ with a stored
+// baseline.
+
+pub fn criterion_dir() -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("target/criterion")
+}
+
+pub fn baseline_file() -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("benches/baseline.txt")
+}
+
+// The mean time of the last run of each bench, in nanoseconds, keyed by names such as
+// "find_mark_move/large".
+fn read_results(dir: &Path) -> Result<BTreeMap<String, f64>> {
+    fn read_json(file: &Path) -> Result<serde_json::Value> {
+        Ok(serde_json::from_str(&std::fs::read_to_string(file)?)
+            .with_context(|_| file.display().to_string())?)
+    }
+
+    fn visit(dir: &Path, results: &mut BTreeMap<String, f64>) -> Result<()> {
+        // criterion keeps the last run in new/, and the name of the bench in benchmark.json as
+        // the directory name has '/' replaced.
+        let new = dir.join("new");
+        if new.join("estimates.json").exists() {
+            let estimates = read_json(&new.join("estimates.json"))?;
+            let mean = estimates["Mean"]["point_estimate"]
+                .as_f64()
+                .ok_or_else(|| failure::format_err!("no mean: {}", new.display()))?;
+            let name = read_json(&new.join("benchmark.json"))?["full_id"]
+                .as_str()
+                .ok_or_else(|| failure::format_err!("no id: {}", new.display()))?
+                .to_string();
+            results.insert(name, mean);
+            return Ok(());
+        }
+        for entry in std::fs::read_dir(dir)? {
+            let path = entry?.path();
+            if path.is_dir() {
+                visit(&path, results)?;
+            }
+        }
+        Ok(())
+    }
+
+    if !dir.exists() {
+        return Err(failure::format_err!(
+            "no bench results in {}; run `cargo bench` first",
+            dir.display()
+        ));
+    }
+    let mut results = BTreeMap::new();
+    visit(dir, &mut results)?;
+    Ok(results)
+}
+
+fn parse_baseline(s: &str) -> Result<BTreeMap<String, f64>> {
+    s.lines()
+        .map(|line| line.trim())
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(|line| {
+            let fields = line.split_whitespace().collect::<Vec<_>>();
+            if fields.len() != 2 {
+                return Err(failure::format_err!("bad baseline: {}", line));
+            }
+            let ns = fields[1]
+                .parse::<f64>()
+                .with_context(|_| format!("bad baseline: {}", line))?;
+            Ok((fields[0].to_string(), ns))
+        })
+        .collect()
+}
+
+fn format_ns(ns: f64) -> String {
+    if ns >= 1e9 {
+        format!("{:.2} s", ns / 1e9)
+    } else if ns >= 1e6 {
+        format!("{:.2} ms", ns / 1e6)
+    } else if ns >= 1e3 {
+        format!("{:.2} us", ns / 1e3)
+    } else {
+        format!("{:.0} ns", ns)
+    }
+}
+
+fn compare(baseline: &BTreeMap<String, f64>, results: &BTreeMap<String, f64>) -> String {
+    let mut lines = vec![format!(
+        "{:<30} {:>12} {:>12} {:>9}",
+        "bench", "baseline", "current", "change"
+    )];
+    for (name, ns) in results {
+        let (base, change) = match baseline.get(name) {
+            Some(base) => (
+                format_ns(*base),
+                format!("{:+.1}%", (ns / base - 1.0) * 100.0),
+            ),
+            None => ("-".to_string(), "new".to_string()),
+        };
+        lines.push(format!(
+            "{:<30} {:>12} {:>12} {:>9}",
+            name,
+            base,
+            format_ns(*ns),
+            change
+        ));
+    }
+    for name in baseline.keys().filter(|name| !results.contains_key(*name)) {
+        lines.push(format!(
+            "{:<30} {:>12} {:>12} {:>9}",
+            name, "", "-", "missing"
+        ));
+    }
+    lines.join("\n")
+}
+
+// Prints the last bench results against the baseline, and with `save`, makes them the baseline.
+pub fn report(dir: &Path, baseline_file: &Path, save: bool) -> Result<()> {
+    let results = read_results(dir)?;
+    let baseline = if baseline_file.exists() {
+        parse_baseline(&std::fs::read_to_string(baseline_file)?)
+            .with_context(|_| baseline_file.display().to_string())?
+    } else {
+        BTreeMap::new()
+    };
+    println!("{}", compare(&baseline, &results));
+    if save {
+        let mut s =
+            "# Mean time of each bench in nanoseconds. Update with `bench-report --save`.\n"
+                .to_string();
+        for (name, ns) in &results {
+            s += &format!("{} {:.0}\n", name, ns);
+        }
+        std::fs::write(baseline_file, s)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn compare_test() -> Result<()> {
+        let baseline = parse_baseline("# comment\nsolve/small 2000000\nplot 100\n")?;
+        let results = vec![
+            ("solve/small".to_string(), 1_500_000.0),
+            ("fill_tour/small".to_string(), 10_000.0),
+        ]
+        .into_iter()
+        .collect();
+        let report = compare(&baseline, &results);
+        let lines = report.lines().collect::<Vec<_>>();
+        assert_eq!(lines.len(), 4);
+        assert!(lines[1].starts_with("fill_tour/small"));
+        assert!(lines[1].ends_with("new"));
+        assert!(lines[2].contains("2.00 ms"));
+        assert!(lines[2].contains("1.50 ms"));
+        assert!(lines[2].ends_with("-25.0%"));
+        assert!(lines[3].starts_with("plot"));
+        assert!(lines[3].ends_with("missing"));
+        assert!(parse_baseline("plot").is_err());
+        Ok(())
+    }
+}
@@ -0,0 +1,213 @@
+use std::io::Write;
+
+use sha2::{Digest, Sha256};
+
+use crate::prelude::*;
+
+pub struct Entry {
+    pub id: TaskId,
+    pub score: usize,
+    pub solution: PathBuf,
+    pub buy: Option<PathBuf>,
+    pub sha256: String,
+}
+
+pub struct Manifest {
+    pub entries: Vec<Entry>,
+    pub zip: PathBuf,
+    pub sha256: String,
+}
+
+impl std::fmt::Display for Manifest {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        for entry in &self.entries {
+            writeln!(
+                f,
+                "prob-{:03}: score: {:8}, sha256: {}, from: {}{}",
+                entry.id,
+                entry.score,
+                entry.sha256,
+                entry.solution.display(),
+                entry
+                    .buy
+                    .as_ref()
+                    .map(|buy| format!(" (with {})", buy.display()))
+                    .unwrap_or_default()
+            )?;
+        }
+        let total: usize = self.entries.iter().map(|entry| entry.score).sum();
+        writeln!(f, "total score: {}", total)?;
+        write!(f, "{}: sha256: {}", self.zip.display(), self.sha256)
+    }
+}
+
+// "best", "lastrun" and "submit" name the directories under contest/. Anything else is a path,
+// such as contest/solution which keeps every run.
+pub fn source_dir(source: &str) -> PathBuf {
+    match source {
+        "best" | "lastrun" | "submit" => PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+            .join("contest")
+            .join(source),
+        _ => PathBuf::from(source),
+    }
+}
+
+// prob-NNN.sol, or prob-NNN-*.sol as the runs are named.
+fn candidates(dir: &Path, id: TaskId) -> Vec<PathBuf> {
+    let exact = format!("prob-{:03}.sol", id);
+    let prefix = format!("prob-{:03}-", id);
+    let mut files = std::fs::read_dir(dir)
+        .into_iter()
+        .flatten()
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| {
+            path.file_name()
+                .and_then(|name| name.to_str())
+                .is_some_and(|name| {
+                    name == exact || name.starts_with(&prefix) && name.ends_with(".sol")
+                })
+        })
+        .collect::<Vec<_>>();
+    files.sort();
+    files
+}
+
+// A valid solution file.
+pub(crate) struct Candidate {
+    pub(crate) file: PathBuf,
+    pub(crate) solution: String,
+    // The .buy file and its contents.
+    pub(crate) buy: Option<(PathBuf, String)>,
+    pub(crate) replay: Replay,
+}
+
+fn sha256(bytes: &[u8]) -> String {
+    format!("{:x}", Sha256::digest(bytes))
+}
+
+// The valid solution of the problem with the fewest turns in the directories. Invalid files are
+// reported and skipped.
+pub(crate) fn best_valid(map: &Map, dirs: &[PathBuf]) -> Result<Option<Candidate>> {
+    let mut best: Option<Candidate> = None;
+    for dir in dirs {
+        for file in candidates(dir, map.id) {
+            let solution = std::fs::read_to_string(&file)?;
+            let buy_file = file.with_extension("buy");
+            let buy = if buy_file.exists() {
+                Some((buy_file.clone(), std::fs::read_to_string(&buy_file)?))
+            } else {
+                None
+            };
+            let replayed = buy
+                .as_ref()
+                .map(|(_, buy)| parse_buy(buy))
+                .transpose()
+                .and_then(|boosters| replay(map.clone(), &solution, &boosters.unwrap_or_default()));
+            match replayed {
+                Ok(replay) => {
+                    if best
+                        .as_ref()
+                        .is_none_or(|best| replay.turns < best.replay.turns)
+                    {
+                        best = Some(Candidate {
+                            file,
+                            solution,
+                            buy,
+                            replay,
+                        });
+                    }
+                }
+                Err(e) => warn!("{}: invalid: {}", file.display(), e),
+            }
+        }
+    }
+    Ok(best)
+}
+
+// Picks the best valid solution of each problem from the sources, and writes them into a zip.
+// Nothing is written unless every problem has a valid solution.
+pub fn bundle(ids: &[TaskId], sources: &[PathBuf], output: &Path) -> Result<Manifest> {
+    let mut chosen = vec![];
+    let mut missing = vec![];
+    for id in ids {
+        let map = Map::new(Task::read_with_id(*id)?);
+        match best_valid(&map, sources)? {
+            Some(best) => chosen.push((*id, best)),
+            None => missing.push(*id),
+        }
+    }
+    if !missing.is_empty() {
+        return Err(failure::format_err!(
+            "no valid solution for problems: {:?}",
+            missing
+        ));
+    }
+
+    let mut zip = zip::ZipWriter::new(std::fs::File::create(output)?);
+    let mut entries = vec![];
+    for (id, candidate) in chosen {
+        let Candidate {
+            file,
+            solution,
+            buy,
+            replay,
+        } = candidate;
+        zip.start_file(format!("prob-{:03}.sol", id), Default::default())?;
+        zip.write_all(solution.as_bytes())?;
+        if let Some((_, buy)) = &buy {
+            zip.start_file(format!("prob-{:03}.buy", id), Default::default())?;
+            zip.write_all(buy.as_bytes())?;
+        }
+        entries.push(Entry {
+            id,
+            score: replay.turns,
+            solution: file,
+            buy: buy.map(|(file, _)| file),
+            sha256: sha256(solution.as_bytes()),
+        });
+    }
+    zip.finish()?;
+
+    Ok(Manifest {
+        entries,
+        zip: output.to_path_buf(),
+        sha256: sha256(&std::fs::read(output)?),
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn bundle_test() -> Result<()> {
+        let dir = std::env::temp_dir().join(format!("icfp2019-bundle-{}", std::process::id()));
+        let (good, bad) = (dir.join("good"), dir.join("bad"));
+        std::fs::create_dir_all(&good)?;
+        std::fs::create_dir_all(&bad)?;
+        for id in 1..=2 {
+            let mut system = System::new(id)?;
+            system.solve()?;
+            std::fs::write(
+                good.join(format!("prob-{:03}.sol", id)),
+                system.solution().solution,
+            )?;
+        }
+        std::fs::write(bad.join("prob-002-score-00000001.sol"), "WWWW")?;
+
+        let output = dir.join("solutions.zip");
+        let manifest = bundle(&[1, 2], &[bad.clone(), good.clone()], &output)?;
+        assert_eq!(manifest.entries.len(), 2);
+        assert!(manifest.entries[1].solution.starts_with(&good));
+        let archive = zip::ZipArchive::new(std::fs::File::open(&output)?)?;
+        assert_eq!(archive.len(), 2);
+
+        // Problem 3 has no solution.
+        std::fs::remove_file(&output)?;
+        assert!(bundle(&[1, 2, 3], &[good], &output).is_err());
+        assert!(!output.exists());
+
+        std::fs::remove_dir_all(&dir)?;
+        Ok(())
+    }
+}
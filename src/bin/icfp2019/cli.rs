@@ -0,0 +1,299 @@
+use structopt::StructOpt;
+
+use crate::prelude::*;
+use crate::select::Selection;
+
+#[derive(StructOpt, Debug)]
+struct Opt {
+    #[structopt(short = "v", parse(from_occurrences))]
+    verbose: u64,
+    #[structopt(subcommand)]
+    cmd: Command,
+}
+
+#[derive(StructOpt, Debug)]
+struct RunOpt {
+    /// Use beam search with this beam width instead of the greedy solver
+    #[structopt(long = "beam-width")]
+    beam_width: Option<usize>,
+    /// Time budget of beam search per problem, in seconds
+    #[structopt(long = "time-budget", default_value = "10")]
+    time_budget: u64,
+    /// Write a trace of every action as .trace.jsonl next to each solution
+    #[structopt(long = "trace")]
+    trace: bool,
+    /// Give up on a problem after this many seconds
+    #[structopt(long = "time-limit")]
+    time_limit: Option<u64>,
+}
+
+impl RunOpt {
+    fn options(&self) -> RunOptions {
+        let strategy = match self.beam_width {
+            Some(width) => Strategy::Beam(BeamOptions {
+                width,
+                time_budget: std::time::Duration::from_secs(self.time_budget),
+            }),
+            None => Strategy::Greedy,
+        };
+        RunOptions {
+            strategy,
+            trace: self.trace,
+            time_limit: self.time_limit.map(std::time::Duration::from_secs),
+        }
+    }
+}
+
+#[derive(StructOpt, Debug)]
+struct ProblemsOpt {
+    /// "all", ids and ranges such as "1-10,42", .desc files such as
+    /// "contest/problem/prob-1*.desc", or "worst:N" for the N problems whose last run is furthest
+    /// behind the best solution
+    #[structopt(long = "problems", default_value = "all")]
+    problems: String,
+}
+
+impl ProblemsOpt {
+    fn ids(&self) -> Result<Vec<TaskId>> {
+        Selection::parse(&self.problems)?.ids()
+    }
+}
+
+#[derive(StructOpt, Debug)]
+enum Command {
+    #[structopt(name = "run")]
+    Run {
+        #[structopt(long = "id")]
+        id: u64,
+        #[structopt(flatten)]
+        options: RunOpt,
+    },
+    /// Solve any .desc file, such as a generated or hand-made map
+    #[structopt(name = "solve")]
+    Solve {
+        /// A .desc file, or - for stdin
+        #[structopt(long = "input", default_value = "-")]
+        input: String,
+        /// The .sol file to write, or - for stdout
+        #[structopt(long = "output", default_value = "-")]
+        output: String,
+        #[structopt(flatten)]
+        options: RunOpt,
+    },
+    #[structopt(name = "run-all")]
+    RunAll {
+        #[structopt(flatten)]
+        options: RunOpt,
+        #[structopt(flatten)]
+        problems: ProblemsOpt,
+    },
+    #[structopt(name = "test-run")]
+    TestRun {
+        #[structopt(long = "id")]
+        id: u64,
+        #[structopt(flatten)]
+        options: RunOpt,
+    },
+    /// Print the scores and the estimated points of the last run and the submission
+    #[structopt(name = "report")]
+    Report {
+        /// Reference turns of each problem, one "id turns" per line. Defaults to
+        /// contest/reference-times.txt of the repository
+        #[structopt(long = "reference")]
+        reference: Option<String>,
+        #[structopt(flatten)]
+        problems: ProblemsOpt,
+    },
+    #[structopt(name = "update-best")]
+    UpdateBest {
+        #[structopt(flatten)]
+        problems: ProblemsOpt,
+    },
+    /// Validate the best solution of every problem and write them into a zip
+    #[structopt(name = "bundle")]
+    Bundle {
+        /// best, lastrun, submit, or a directory. Can be given more than once.
+        #[structopt(long = "from")]
+        from: Vec<String>,
+        #[structopt(long = "output", default_value = "contest/submit/solutions.zip")]
+        output: String,
+        #[structopt(flatten)]
+        problems: ProblemsOpt,
+    },
+    /// Compare the solutions in two directories by replaying them
+    #[structopt(name = "diff")]
+    Diff {
+        /// best, lastrun, submit, or a directory
+        a: String,
+        /// best, lastrun, submit, or a directory
+        b: String,
+        #[structopt(flatten)]
+        problems: ProblemsOpt,
+    },
+    /// Solve a block of the local lambda chain and submit the results to it
+    #[structopt(name = "mine")]
+    Mine {
+        #[structopt(long = "chain", default_value = "contest/chain")]
+        chain: String,
+        /// The latest block if not given
+        #[structopt(long = "block")]
+        block: Option<u64>,
+    },
+    /// Check the default solver against the snapshots, or bless its current output
+    #[structopt(name = "snapshot")]
+    Snapshot {
+        /// Record the current output as expected
+        #[structopt(long = "bless")]
+        bless: bool,
+        /// Problems to bless. The problems already in the snapshots if not given.
+        #[structopt(long = "problems")]
+        problems: Option<String>,
+    },
+    /// Generate a random task as a .desc file
+    #[structopt(name = "generate")]
+    Generate {
+        /// The map fits in a size x size square
+        #[structopt(long = "size", default_value = "30")]
+        size: i32,
+        /// Cells eroded from the border of the square
+        #[structopt(long = "erosion", default_value = "100")]
+        erosion: usize,
+        #[structopt(long = "obstacles", default_value = "5")]
+        obstacles: usize,
+        /// Boosters of each kind
+        #[structopt(long = "boosters", default_value = "1")]
+        boosters: usize,
+        #[structopt(long = "seed", default_value = "0")]
+        seed: u64,
+        /// The .desc file to write, or - for stdout
+        #[structopt(long = "output", default_value = "-")]
+        output: String,
+    },
+    /// Compare the results of the last `cargo bench` with the baseline in benches/baseline.txt
+    #[structopt(name = "bench-report")]
+    BenchReport {
+        /// Make the last results the baseline
+        #[structopt(long = "save")]
+        save: bool,
+    },
+    /// Summarize traces: idle turns, moves over painted cells and booster pickup latency
+    #[structopt(name = "trace-summary")]
+    TraceSummary {
+        /// .trace.jsonl files, or directories containing them
+        paths: Vec<String>,
+    },
+}
+
+/// Runs the command line of the icfp2019 binary.
+pub fn main() -> Result<()> {
+    let opt = Opt::from_args();
+    loggerv::init_with_verbosity(opt.verbose).unwrap();
+    match opt.cmd {
+        Command::Run { id, options } => crate::contest::run(id, &options.options()),
+        Command::TestRun { id, options } => crate::contest::test_run(id, &options.options()),
+        Command::Solve {
+            input,
+            output,
+            options,
+        } => crate::contest::solve_file(&input, &output, &options.options()).map(|_| ()),
+        Command::RunAll { options, problems } => {
+            crate::contest::run_all(&problems.ids()?, &options.options())
+        }
+        Command::Report {
+            reference,
+            problems,
+        } => {
+            let reference = reference
+                .map(PathBuf::from)
+                .unwrap_or_else(crate::contest::reference_times_file);
+            crate::contest::report(&problems.ids()?, &reference)
+        }
+        Command::UpdateBest { problems } => crate::contest::update_best(&problems.ids()?),
+        Command::Bundle {
+            from,
+            output,
+            problems,
+        } => {
+            let sources = if from.is_empty() {
+                vec!["lastrun".to_string()]
+            } else {
+                from
+            };
+            let sources = sources
+                .iter()
+                .map(|source| crate::bundle::source_dir(source))
+                .collect::<Vec<_>>();
+            let ids = problems.ids()?;
+            let manifest = crate::bundle::bundle(&ids, &sources, Path::new(&output))?;
+            println!("{}", manifest);
+            Ok(())
+        }
+        Command::Diff { a, b, problems } => {
+            let ids = problems.ids()?;
+            let diff = crate::diff::diff(
+                &ids,
+                &crate::bundle::source_dir(&a),
+                &crate::bundle::source_dir(&b),
+            )?;
+            println!("{}", diff);
+            Ok(())
+        }
+        Command::Mine { chain, block } => {
+            crate::mine::mine(&crate::mine::LocalChain::new(chain), block).map(|_| ())
+        }
+        Command::Snapshot { bless, problems } => {
+            let dir = crate::snapshot::snapshot_dir();
+            if bless {
+                let ids = match problems {
+                    Some(problems) => Selection::parse(&problems)?.ids()?,
+                    None => vec![],
+                };
+                return crate::snapshot::bless(&dir, &ids);
+            }
+            let mismatches = crate::snapshot::check(&dir)?;
+            for mismatch in &mismatches {
+                println!("{}", mismatch);
+            }
+            if mismatches.is_empty() {
+                Ok(())
+            } else {
+                Err(failure::format_err!(
+                    "{} problems differ from the snapshots",
+                    mismatches.len()
+                ))
+            }
+        }
+        Command::Generate {
+            size,
+            erosion,
+            obstacles,
+            boosters,
+            seed,
+            output,
+        } => {
+            let options = GenerateOptions {
+                size,
+                erosion,
+                obstacles,
+                boosters,
+                seed,
+            };
+            let task = generate(0, &options)?;
+            if output == "-" {
+                println!("{}", task);
+            } else {
+                std::fs::write(&output, task.to_string())?;
+            }
+            Ok(())
+        }
+        Command::BenchReport { save } => crate::bench_report::report(
+            &crate::bench_report::criterion_dir(),
+            &crate::bench_report::baseline_file(),
+            save,
+        ),
+        Command::TraceSummary { paths } => {
+            let paths = paths.iter().map(PathBuf::from).collect::<Vec<_>>();
+            crate::trace_summary::trace_summary(&paths).map(|_| ())
+        }
+    }
+}
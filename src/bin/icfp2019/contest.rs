@@ -0,0 +1,424 @@
+use rayon::prelude::*;
+use regex::Regex;
+
+use crate::prelude::*;
+
+pub fn solve(id: u64, options: &RunOptions) -> Result<Solution> {
+    solve_task(Task::read_with_id(id)?, options)
+}
+
+pub fn write_solution(solution: &Solution) -> Result<()> {
+    let dir = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+
+    let file = dir.join("contest/solution").join(&solution.filename);
+    info!("write solution: {}", file.display());
+    std::fs::write(&file, &solution.solution)?;
+    write_trace(solution, &file)?;
+
+    let file = dir.join(format!("contest/lastrun/prob-{:03}.sol", solution.id));
+    info!("write solution: {}", file.display());
+    std::fs::write(&file, &solution.solution)?;
+    write_trace(solution, &file)?;
+
+    Ok(())
+}
+
+// Writes the trace, if any, as prob-NNN.trace.jsonl next to prob-NNN.sol.
+fn write_trace(solution: &Solution, file: &Path) -> Result<()> {
+    if let Some(trace) = &solution.trace {
+        let file = file.with_extension("trace.jsonl");
+        info!("write trace: {}", file.display());
+        std::fs::write(file, trace)?;
+    }
+    Ok(())
+}
+
+// Solves a .desc file into a .sol file, either of which can be "-" for stdin or stdout. The
+// trace, if asked for, is written next to the output file.
+pub fn solve_file(input: &str, output: &str, options: &RunOptions) -> Result<Solution> {
+    let (id, s) = if input == "-" {
+        let mut s = String::new();
+        std::io::Read::read_to_string(&mut std::io::stdin(), &mut s)?;
+        (0, s)
+    } else {
+        let path = Path::new(input);
+        let id = path
+            .file_name()
+            .and_then(|name| name.to_str())
+            .and_then(Task::id_from_file_name)
+            .unwrap_or(0);
+        (id, std::fs::read_to_string(path)?)
+    };
+    let task = Task::parse(id, &s).with_context(|_| format!("{}: bad task", input))?;
+    let solution = solve_task(task, options)?;
+    info!("solved: {}, score: {}", input, solution.score);
+
+    if output == "-" {
+        println!("{}", solution.solution);
+        if solution.trace.is_some() {
+            warn!("no trace is written with the solution to stdout");
+        }
+    } else {
+        let file = Path::new(output);
+        std::fs::write(file, &solution.solution)?;
+        write_trace(&solution, file)?;
+    }
+    Ok(solution)
+}
+
+// The turns a solution takes: the length of the longest action list of a bot.
+fn solution_score(solution: &str) -> usize {
+    lazy_static! {
+        static ref POS_RE: Regex = Regex::new(r"\(-?\d+,-?\d+\)").unwrap();
+    }
+    let remove_points = POS_RE.replace_all(solution.trim(), "");
+    remove_points
+        .split('#')
+        .map(|actions| actions.chars().count())
+        .max()
+        .unwrap_or(0)
+}
+
+pub fn read_solution(id: u64, path: impl AsRef<Path>) -> Result<Solution> {
+    let solution = std::fs::read_to_string(path.as_ref())?;
+    Ok(Solution {
+        id,
+        score: solution_score(&solution),
+        solution: solution.trim().to_string(),
+        filename: path.as_ref().display().to_string(),
+        trace: None,
+    })
+}
+
+pub fn update_best(ids: &[TaskId]) -> Result<()> {
+    let dir = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+    for &id in ids {
+        let submit_file = dir.join(format!("contest/submit/prob-{:03}.sol", id));
+        let submit_solution = read_solution(id, submit_file)?;
+
+        let best_file = dir.join(format!("contest/best/prob-{:03}.sol", id));
+        if !best_file.exists() {
+            std::fs::write(best_file, &submit_solution.solution)?;
+        } else {
+            let best_solution = read_solution(id, &best_file)?;
+            if submit_solution.score < best_solution.score {
+                println!(
+                    "Updating... id: {}, submit score: {} < best score: {}",
+                    id, submit_solution.score, best_solution.score
+                );
+                std::fs::write(&best_file, &submit_solution.solution)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+fn best_score_for(id: u64) -> Result<usize> {
+    let dir = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+    let best_file = dir.join(format!("contest/best/prob-{:03}.sol", id));
+    if !best_file.exists() {
+        return Err(failure::format_err!("no best file"));
+    }
+    let solution = read_solution(id, &best_file)?;
+    Ok(solution.score)
+}
+
+pub fn run(id: u64, options: &RunOptions) -> Result<()> {
+    println!("> Sovling: {}", id);
+    let solution = solve(id, options)?;
+    write_solution(&solution)?;
+
+    if let Ok(best_score) = best_score_for(id) {
+        if solution.score == best_score {
+            println!(
+                "> Done: id: {:03}, score: {}, best_score {} (=)",
+                id, solution.score, best_score
+            );
+        } else if solution.score < best_score {
+            println!(
+                "> Done: id: {:03}, score: {}, best_score: {} (New!)",
+                id, solution.score, best_score
+            );
+        } else {
+            println!(
+                "> Done: id: {:03}, score: {}, best_score {}",
+                id, solution.score, best_score
+            );
+        }
+    } else {
+        println!("> Done: id: {:03}, score: {}", id, solution.score);
+    }
+
+    Ok(())
+}
+
+// How a problem of run_all ended.
+#[derive(Debug, PartialEq)]
+pub enum Outcome {
+    Solved,
+    Failed(String),
+    Panicked(String),
+    TimedOut,
+}
+
+impl std::fmt::Display for Outcome {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Outcome::Solved => write!(f, "solved"),
+            Outcome::Failed(e) => write!(f, "failed: {}", e),
+            Outcome::Panicked(message) => write!(f, "panicked: {}", message),
+            Outcome::TimedOut => write!(f, "timed out"),
+        }
+    }
+}
+
+// Runs f, catching its errors and panics so that one problem does not stop the others.
+fn isolate(f: impl FnOnce() -> Result<()>) -> Outcome {
+    match std::panic::catch_unwind(std::panic::AssertUnwindSafe(f)) {
+        Ok(Ok(())) => Outcome::Solved,
+        Ok(Err(e)) => {
+            if e.find_root_cause().downcast_ref::<Timeout>().is_some() {
+                Outcome::TimedOut
+            } else {
+                Outcome::Failed(e.to_string())
+            }
+        }
+        Err(payload) => Outcome::Panicked(
+            payload
+                .downcast_ref::<&str>()
+                .map(|message| message.to_string())
+                .or_else(|| payload.downcast_ref::<String>().cloned())
+                .unwrap_or_else(|| "unknown panic".to_string()),
+        ),
+    }
+}
+
+// Solves every problem, and fails at the end if any of them failed. The solutions found are
+// written anyway.
+pub fn run_all(ids: &[TaskId], options: &RunOptions) -> Result<()> {
+    let outcomes = ids
+        .par_iter()
+        .map(|&id| {
+            let outcome = isolate(|| run(id, options));
+            if outcome != Outcome::Solved {
+                println!("> Failed: id: {:03}, {}", id, outcome);
+            }
+            (id, outcome)
+        })
+        .collect::<Vec<_>>();
+
+    let count = |f: fn(&Outcome) -> bool| outcomes.iter().filter(|(_, o)| f(o)).count();
+    let solved = count(|o| *o == Outcome::Solved);
+    let failed = count(|o| matches!(o, Outcome::Failed(_)));
+    let panicked = count(|o| matches!(o, Outcome::Panicked(_)));
+    let timed_out = count(|o| *o == Outcome::TimedOut);
+    println!(
+        "> Summary: solved: {}, failed: {}, panicked: {}, timed out: {}",
+        solved, failed, panicked, timed_out
+    );
+    for (id, outcome) in &outcomes {
+        if *outcome != Outcome::Solved {
+            println!("  id: {:03}, {}", id, outcome);
+        }
+    }
+    if solved < outcomes.len() {
+        return Err(failure::format_err!(
+            "{} of {} problems were not solved",
+            outcomes.len() - solved,
+            outcomes.len()
+        ));
+    }
+    Ok(())
+}
+
+pub fn test_run(id: u64, options: &RunOptions) -> Result<()> {
+    println!("> Sovling: {}", id);
+    let solution = solve(id, options)?;
+    println!("> Done: id: {:03}, score: {}", id, solution.score);
+
+    let dir = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+    let file = dir.join("contest/testrun").join(&solution.filename);
+    println!("write solution: {}", file.display());
+    std::fs::write(&file, &solution.solution)?;
+    write_trace(&solution, &file)?;
+    Ok(())
+}
+
+pub fn reference_times_file() -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("contest/reference-times.txt")
+}
+
+// Reference turns of each problem, one "id turns" per line, such as the best times of the
+// contest. Lines starting with '#' are comments.
+pub fn parse_reference_times(s: &str) -> Result<HashMap<u64, usize>> {
+    s.lines()
+        .map(|line| line.trim())
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(|line| {
+            let fields = line.split_whitespace().collect::<Vec<_>>();
+            if fields.len() != 2 {
+                return Err(failure::format_err!("bad reference time: {}", line));
+            }
+            Ok((fields[0].parse()?, fields[1].parse()?))
+        })
+        .collect()
+}
+
+// Points of a problem in the contest's scoring, relative to the best time of all teams.
+pub fn estimate_points(size_x: i32, size_y: i32, t_best: usize, t_ours: usize) -> f64 {
+    1000.0 * f64::from(size_x * size_y).log2() * t_best.min(t_ours) as f64 / t_ours as f64
+}
+
+// Prints the estimated points of each problem, using the reference times where known and our
+// best score otherwise, and then the problems which can gain the most points.
+pub fn report(ids: &[TaskId], reference: &Path) -> Result<()> {
+    let reference = if reference.exists() {
+        parse_reference_times(&std::fs::read_to_string(reference)?)?
+    } else {
+        warn!("no reference times: {}", reference.display());
+        HashMap::new()
+    };
+
+    let dir = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+    let sizes = ids
+        .iter()
+        .map(|id| {
+            let task = Task::read_with_id(*id)?;
+            let map = Map::new(task);
+            Ok((map.max_x, map.max_y))
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    for sub in &["lastrun", "submit"] {
+        println!("{}:", sub);
+        let mut total = 0.0;
+        let mut gains = vec![];
+        for (id, (size_x, size_y)) in ids.iter().zip(&sizes) {
+            let file = dir.join(format!("contest/{}/prob-{:03}.sol", sub, id));
+            if !file.exists() {
+                continue;
+            }
+            let solution = read_solution(*id, file)?;
+
+            let best_score = best_score_for(*id);
+            let info = if let Ok(best_score) = best_score {
+                if solution.score < best_score {
+                    "(New!)"
+                } else if solution.score == best_score {
+                    "(*)"
+                } else {
+                    ""
+                }
+            } else {
+                ""
+            };
+
+            let t_best = reference
+                .get(id)
+                .cloned()
+                .or_else(|| best_score.as_ref().ok().cloned())
+                .unwrap_or(solution.score);
+            let points = estimate_points(*size_x, *size_y, t_best, solution.score);
+            let max_points = estimate_points(*size_x, *size_y, 1, 1);
+            total += points;
+            gains.push((max_points - points, *id));
+
+            println!(
+                "id: {:03}, score: {} (best: {}) {}, points: {:.0} / {:.0}",
+                id,
+                solution.score,
+                best_score.unwrap_or(0),
+                info,
+                points,
+                max_points
+            );
+        }
+        println!("total points: {:.0}", total);
+
+        gains.sort_by(|a, b| b.0.partial_cmp(&a.0).unwrap());
+        println!("potential gains:");
+        for (gain, id) in gains.iter().take(10) {
+            println!("id: {:03}, +{:.0}", id, gain);
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn solve_file_test() -> Result<()> {
+        let dir = std::env::temp_dir().join(format!("icfp2019-solve-{}", std::process::id()));
+        std::fs::create_dir_all(&dir)?;
+        let (input, output) = (dir.join("map.desc"), dir.join("map.sol"));
+        std::fs::write(&input, Task::read_with_id(2)?.to_string())?;
+        let options = RunOptions {
+            strategy: Strategy::Greedy,
+            trace: true,
+            time_limit: None,
+        };
+        let solution = solve_file(input.to_str().unwrap(), output.to_str().unwrap(), &options)?;
+        let map = Map::new(Task::read_from(0, &input)?);
+        let replay = replay(map, &std::fs::read_to_string(&output)?, &[])?;
+        assert_eq!(replay.turns, solution.score);
+        assert!(dir.join("map.trace.jsonl").exists());
+
+        std::fs::remove_dir_all(&dir)?;
+        Ok(())
+    }
+
+    #[test]
+    fn solution_score_test() {
+        assert_eq!(solution_score("WDB(1,-2)F#QZ\n"), 4);
+        assert_eq!(solution_score(""), 0);
+    }
+
+    proptest::proptest! {
+        #[test]
+        fn solution_score_fuzz_test(s in "[WSADZEQFLCB(),#0-9-]{0,40}|\\PC*") {
+            solution_score(&s);
+        }
+    }
+
+    #[test]
+    fn isolate_test() {
+        assert_eq!(isolate(|| Ok(())), Outcome::Solved);
+        assert_eq!(
+            isolate(|| Err(failure::err_msg("no path"))),
+            Outcome::Failed("no path".to_string())
+        );
+        assert_eq!(
+            isolate(|| panic!("fast wheels")),
+            Outcome::Panicked("fast wheels".to_string())
+        );
+        let options = RunOptions {
+            strategy: Strategy::Greedy,
+            trace: false,
+            time_limit: Some(Duration::from_secs(0)),
+        };
+        assert_eq!(
+            isolate(|| solve(2, &options).map(|_| ())),
+            Outcome::TimedOut
+        );
+    }
+
+    #[test]
+    fn estimate_points_test() {
+        assert_eq!(estimate_points(16, 16, 100, 100), 8000.0);
+        assert_eq!(estimate_points(16, 16, 100, 200), 4000.0);
+        // Beating the reference is capped.
+        assert_eq!(estimate_points(16, 16, 100, 50), 8000.0);
+    }
+
+    #[test]
+    fn parse_reference_times_test() -> Result<()> {
+        let times = parse_reference_times("# id turns\n1 13\n\n2  105\n")?;
+        assert_eq!(times.len(), 2);
+        assert_eq!(times[&2], 105);
+        assert!(parse_reference_times("1").is_err());
+        Ok(())
+    }
+}
@@ -0,0 +1,141 @@
+use crate::bundle::*;
+use crate::prelude::*;
+
+pub struct ProblemDiff {
+    pub id: TaskId,
+    // None if there is no valid solution.
+    pub a: Option<Replay>,
+    pub b: Option<Replay>,
+}
+
+pub struct Diff {
+    pub problems: Vec<ProblemDiff>,
+}
+
+// Validates the solutions of each problem in both directories by replaying them.
+pub fn diff(ids: &[TaskId], a: &Path, b: &Path) -> Result<Diff> {
+    let mut problems = vec![];
+    for id in ids {
+        let map = Map::new(Task::read_with_id(*id)?);
+        let replay = |dir: &Path| -> Result<Option<Replay>> {
+            Ok(best_valid(&map, &[dir.to_path_buf()])?.map(|candidate| candidate.replay))
+        };
+        problems.push(ProblemDiff {
+            id: *id,
+            a: replay(a)?,
+            b: replay(b)?,
+        });
+    }
+    Ok(Diff { problems })
+}
+
+impl ProblemDiff {
+    // Boosters used a different number of times, as "kind: a -> b".
+    fn booster_changes(&self) -> Vec<String> {
+        let (a, b) = match (&self.a, &self.b) {
+            (Some(a), Some(b)) => (a, b),
+            _ => return vec![],
+        };
+        let mut kinds = a.used.keys().chain(b.used.keys()).collect::<Vec<_>>();
+        kinds.sort();
+        kinds.dedup();
+        kinds
+            .into_iter()
+            .filter_map(|kind| {
+                let count_a = a.used.get(kind).cloned().unwrap_or(0);
+                let count_b = b.used.get(kind).cloned().unwrap_or(0);
+                if count_a == count_b {
+                    None
+                } else {
+                    Some(format!("{}: {} -> {}", kind, count_a, count_b))
+                }
+            })
+            .collect()
+    }
+}
+
+impl std::fmt::Display for Diff {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        let turns = |replay: &Option<Replay>| {
+            replay
+                .as_ref()
+                .map(|replay| replay.turns.to_string())
+                .unwrap_or_else(|| "-".to_string())
+        };
+        let (mut total_a, mut total_b) = (0, 0);
+        let (mut better, mut worse, mut same, mut missing) = (0, 0, 0, 0);
+        for problem in &self.problems {
+            let delta = match (&problem.a, &problem.b) {
+                (Some(a), Some(b)) => {
+                    total_a += a.turns;
+                    total_b += b.turns;
+                    match b.turns.cmp(&a.turns) {
+                        std::cmp::Ordering::Less => better += 1,
+                        std::cmp::Ordering::Greater => worse += 1,
+                        std::cmp::Ordering::Equal => same += 1,
+                    }
+                    format!("{:+}", b.turns as i64 - a.turns as i64)
+                }
+                _ => {
+                    missing += 1;
+                    "-".to_string()
+                }
+            };
+            let changes = problem.booster_changes();
+            writeln!(
+                f,
+                "id: {:03}, a: {}, b: {}, delta: {}{}",
+                problem.id,
+                turns(&problem.a),
+                turns(&problem.b),
+                delta,
+                if changes.is_empty() {
+                    "".to_string()
+                } else {
+                    format!(", boosters: {}", changes.join(", "))
+                }
+            )?;
+        }
+        write!(
+            f,
+            "total: a: {}, b: {}, delta: {:+} (better: {}, worse: {}, same: {}, missing: {})",
+            total_a,
+            total_b,
+            total_b as i64 - total_a as i64,
+            better,
+            worse,
+            same,
+            missing
+        )
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn diff_test() -> Result<()> {
+        let dir = std::env::temp_dir().join(format!("icfp2019-diff-{}", std::process::id()));
+        let (a, b) = (dir.join("a"), dir.join("b"));
+        std::fs::create_dir_all(&a)?;
+        std::fs::create_dir_all(&b)?;
+        let mut system = System::new(2)?;
+        system.solve()?;
+        let solution = system.solution().solution;
+        std::fs::write(a.join("prob-002.sol"), &solution)?;
+        // The same solution with a turn wasted at the start.
+        std::fs::write(b.join("prob-002.sol"), format!("Z{}", solution))?;
+
+        let diff = diff(&[1, 2], &a, &b)?;
+        assert!(diff.problems[0].a.is_none());
+        assert_eq!(
+            diff.problems[1].b.as_ref().unwrap().turns,
+            diff.problems[1].a.as_ref().unwrap().turns + 1
+        );
+        assert!(diff.to_string().ends_with("worse: 1, same: 0, missing: 1)"));
+
+        std::fs::remove_dir_all(&dir)?;
+        Ok(())
+    }
+}
@@ -0,0 +1,14 @@
+mod bench_report;
+mod bundle;
+mod cli;
+mod contest;
+mod diff;
+mod mine;
+mod prelude;
+mod select;
+mod snapshot;
+mod trace_summary;
+
+fn main() -> icfp2019::Result<()> {
+    cli::main()
+}
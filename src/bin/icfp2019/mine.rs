@@ -0,0 +1,99 @@
+use crate::prelude::*;
+
+// A file-based stand-in for the lambda chain client:
+//
+//   <dir>/blocks/<block>/puzzle.cond       the block's puzzle
+//   <dir>/blocks/<block>/task.desc         the block's task
+//   <dir>/submissions/<block>/task.sol     our solution of the task
+//   <dir>/submissions/<block>/puzzle.desc  our task satisfying the puzzle
+pub struct LocalChain {
+    dir: PathBuf,
+}
+
+pub struct BlockInfo {
+    pub block: u64,
+    pub puzzle: String,
+    pub task: String,
+}
+
+impl LocalChain {
+    pub fn new(dir: impl AsRef<Path>) -> LocalChain {
+        LocalChain {
+            dir: dir.as_ref().to_path_buf(),
+        }
+    }
+
+    // The latest block unless a block is given.
+    pub fn block_info(&self, block: Option<u64>) -> Result<BlockInfo> {
+        let block = match block {
+            Some(block) => block,
+            None => std::fs::read_dir(self.dir.join("blocks"))?
+                .filter_map(|entry| entry.ok()?.file_name().to_str()?.parse().ok())
+                .max()
+                .ok_or_else(|| failure::err_msg("no blocks in the chain"))?,
+        };
+        let dir = self.dir.join(format!("blocks/{}", block));
+        Ok(BlockInfo {
+            block,
+            puzzle: std::fs::read_to_string(dir.join("puzzle.cond"))
+                .with_context(|_| format!("block {}: puzzle.cond", block))?,
+            task: std::fs::read_to_string(dir.join("task.desc"))
+                .with_context(|_| format!("block {}: task.desc", block))?,
+        })
+    }
+
+    pub fn submit(&self, block: u64, solution: &str, puzzle: &str) -> Result<PathBuf> {
+        let dir = self.dir.join(format!("submissions/{}", block));
+        std::fs::create_dir_all(&dir)?;
+        std::fs::write(dir.join("task.sol"), solution)?;
+        std::fs::write(dir.join("puzzle.desc"), puzzle)?;
+        Ok(dir)
+    }
+}
+
+pub fn mine(chain: &LocalChain, block: Option<u64>) -> Result<PathBuf> {
+    let info = chain.block_info(block)?;
+    info!("mine: block {}", info.block);
+
+    let task = Task::parse(info.block, &info.task)?;
+    let solution = solve_task(task, &RunOptions::default())?;
+    info!("mine: task solved in {} turns", solution.score);
+
+    let answer = Puzzle::parse(&info.puzzle)?.solve()?;
+    let dir = chain.submit(info.block, &solution.solution, &answer.to_string())?;
+    info!("mine: submitted to {}", dir.display());
+    Ok(dir)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn mine_test() -> Result<()> {
+        let dir = std::env::temp_dir().join(format!("icfp2019-chain-{}", std::process::id()));
+        let root = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+        for block in 1..=2 {
+            let block_dir = dir.join(format!("blocks/{}", block));
+            std::fs::create_dir_all(&block_dir)?;
+            std::fs::write(
+                block_dir.join("puzzle.cond"),
+                "2,1,10,4,20,1,0,0,0,1,1#(1,1),(8,8)#(5,0),(0,5)",
+            )?;
+            std::fs::copy(
+                root.join(format!("contest/problem/prob-{:03}.desc", block)),
+                block_dir.join("task.desc"),
+            )?;
+        }
+
+        let chain = LocalChain::new(&dir);
+        let submitted = mine(&chain, None)?;
+        assert_eq!(submitted, dir.join("submissions/2"));
+        let puzzle = Task::parse(2, &std::fs::read_to_string(submitted.join("puzzle.desc"))?)?;
+        assert_eq!(puzzle.boosters().len(), 3);
+        assert!(!std::fs::read_to_string(submitted.join("task.sol"))?.is_empty());
+
+        std::fs::remove_dir_all(&dir)?;
+        Ok(())
+    }
+}
@@ -0,0 +1,7 @@
+pub use failure::ResultExt; // For .context()
+pub use icfp2019::*;
+pub use lazy_static::*;
+pub use log::*;
+pub use std::collections::{HashMap, HashSet, VecDeque};
+pub use std::path::Path;
+pub use std::path::PathBuf;
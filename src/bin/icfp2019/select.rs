@@ -0,0 +1,186 @@
+use crate::contest::read_solution;
+use crate::prelude::*;
+
+// Which problems a command works on.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Selection {
+    // Every problem in contest/problem.
+    All,
+    // Ids and ranges, such as "1-10,42".
+    Ids(Vec<TaskId>),
+    // .desc files matching a pattern, such as "contest/problem/prob-1*.desc". Only the file name
+    // can have wildcards, and the files must be in contest/problem.
+    Glob(PathBuf),
+    // The problems whose last run is furthest behind the best solution, such as "worst:10".
+    Worst(usize),
+}
+
+impl Selection {
+    pub fn parse(s: &str) -> Result<Selection> {
+        let s = s.trim();
+        if s == "all" {
+            return Ok(Selection::All);
+        }
+        if let Some(n) = s.strip_prefix("worst:") {
+            return Ok(Selection::Worst(n.parse()?));
+        }
+        if s.contains('*') || s.contains('?') || s.ends_with(".desc") {
+            return Ok(Selection::Glob(PathBuf::from(s)));
+        }
+        let mut ids = vec![];
+        for part in s.split(',') {
+            let part = part.trim();
+            match part.find('-') {
+                Some(i) => {
+                    let (first, last): (TaskId, TaskId) =
+                        (part[..i].parse()?, part[i + 1..].parse()?);
+                    if first > last {
+                        return Err(failure::format_err!("empty range: {}", part));
+                    }
+                    ids.extend(first..=last);
+                }
+                None => ids.push(part.parse()?),
+            }
+        }
+        Ok(Selection::Ids(ids))
+    }
+
+    // The selected ids, in order and without duplicates. Every id has a problem.
+    pub fn ids(&self) -> Result<Vec<TaskId>> {
+        let available = Task::ids()?;
+        let mut ids = match self {
+            Selection::All => available.clone(),
+            Selection::Ids(ids) => ids.clone(),
+            Selection::Glob(pattern) => glob(pattern)?,
+            Selection::Worst(n) => worst(&available, *n)?,
+        };
+        if let Some(id) = ids.iter().find(|id| !available.contains(id)) {
+            return Err(failure::format_err!("no such problem: {}", id));
+        }
+        // The worst problems come first.
+        if !matches!(self, Selection::Worst(_)) {
+            ids.sort();
+        }
+        let mut seen = HashSet::new();
+        ids.retain(|id| seen.insert(*id));
+        Ok(ids)
+    }
+}
+
+fn glob(pattern: &Path) -> Result<Vec<TaskId>> {
+    let dir = match pattern.parent() {
+        Some(dir) if dir != Path::new("") => dir.to_path_buf(),
+        _ => PathBuf::from("."),
+    };
+    // Problems are passed on by id, which only stands for the file of contest/problem.
+    let problems = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("contest/problem");
+    if dir.canonicalize()? != problems.canonicalize()? {
+        return Err(failure::format_err!(
+            "not a pattern of {}: {}",
+            problems.display(),
+            pattern.display()
+        ));
+    }
+    let name = pattern
+        .file_name()
+        .and_then(|name| name.to_str())
+        .ok_or_else(|| failure::format_err!("bad pattern: {}", pattern.display()))?;
+    let mut ids = vec![];
+    for entry in std::fs::read_dir(&dir)? {
+        let file_name = entry?.file_name().into_string().unwrap_or_default();
+        if wildcard_match(name, &file_name) {
+            if let Some(id) = Task::id_from_file_name(&file_name) {
+                ids.push(id);
+            }
+        }
+    }
+    Ok(ids)
+}
+
+// Matches '*' to any characters and '?' to one character.
+fn wildcard_match(pattern: &str, name: &str) -> bool {
+    let (pattern, name) = (pattern.as_bytes(), name.as_bytes());
+    // matched[j]: pattern[..i] matches name[..j].
+    let mut matched = vec![false; name.len() + 1];
+    matched[0] = true;
+    for c in pattern {
+        let mut next = vec![false; name.len() + 1];
+        for j in 0..=name.len() {
+            next[j] = match c {
+                b'*' => matched[j] || j > 0 && next[j - 1],
+                b'?' => j > 0 && matched[j - 1],
+                c => j > 0 && matched[j - 1] && name[j - 1] == *c,
+            };
+        }
+        matched = next;
+    }
+    matched[name.len()]
+}
+
+// The n problems with the largest ratio of the last run's score to the best score. Problems
+// without both solutions are left out.
+fn worst(ids: &[TaskId], n: usize) -> Result<Vec<TaskId>> {
+    let dir = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("contest");
+    let mut ratios = vec![];
+    for id in ids {
+        let lastrun = dir.join(format!("lastrun/prob-{:03}.sol", id));
+        let best = dir.join(format!("best/prob-{:03}.sol", id));
+        if !lastrun.exists() || !best.exists() {
+            continue;
+        }
+        let ours = read_solution(*id, lastrun)?.score;
+        let best = read_solution(*id, best)?.score;
+        ratios.push((ours as f64 / best.max(1) as f64, *id));
+    }
+    ratios.sort_by(|a, b| b.0.partial_cmp(&a.0).unwrap().then(a.1.cmp(&b.1)));
+    Ok(ratios.into_iter().take(n).map(|(_, id)| id).collect())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn parse_selection_test() -> Result<()> {
+        assert_eq!(Selection::parse("all")?, Selection::All);
+        assert_eq!(
+            Selection::parse("1-3,7, 2")?,
+            Selection::Ids(vec![1, 2, 3, 7, 2])
+        );
+        assert_eq!(Selection::parse("worst:5")?, Selection::Worst(5));
+        assert_eq!(
+            Selection::parse("contest/problem/prob-00*.desc")?,
+            Selection::Glob(PathBuf::from("contest/problem/prob-00*.desc"))
+        );
+        assert!(Selection::parse("3-1").is_err());
+        assert!(Selection::parse("x").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn wildcard_match_test() {
+        assert!(wildcard_match("prob-00*.desc", "prob-001.desc"));
+        assert!(wildcard_match("prob-00?.desc", "prob-001.desc"));
+        assert!(!wildcard_match("prob-00?.desc", "prob-0010.desc"));
+        assert!(!wildcard_match("prob-1*.desc", "prob-001.desc"));
+        assert!(wildcard_match("*", ""));
+    }
+
+    #[test]
+    fn selection_ids_test() -> Result<()> {
+        assert_eq!(Selection::parse("3,1-2,2")?.ids()?, vec![1, 2, 3]);
+        let dir = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("contest/problem");
+        assert_eq!(
+            Selection::Glob(dir.join("prob-00?.desc")).ids()?,
+            Task::ids()?
+                .into_iter()
+                .filter(|id| *id < 10)
+                .collect::<Vec<_>>()
+        );
+        assert!(Selection::Glob(dir.join("../best/prob-00?.desc"))
+            .ids()
+            .is_err());
+        assert!(Selection::Ids(vec![100_000]).ids().is_err());
+        Ok(())
+    }
+}
@@ -0,0 +1,218 @@
+use std::collections::BTreeMap;
+
+use crate::contest::solve;
+use crate::prelude::*;
+
+// Expected outputs of the default solver, checked by snapshot_test. scores.txt has one "id turns"
+// line per problem, or "id -" for a problem to bless whose turns are not recorded yet, and
+// prob-NNN.sol, where present, the expected solution. Problems missing from contest/problem, such
+// as the ones of the full contest set in a partial checkout, are skipped.
+pub fn snapshot_dir() -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("snapshots")
+}
+
+pub struct Mismatch {
+    pub id: TaskId,
+    // None if the snapshot has no score for the problem yet.
+    pub expected: Option<usize>,
+    pub actual: usize,
+    // Where the solutions differ, if the snapshot has one.
+    pub solution_diff: Option<String>,
+}
+
+impl std::fmt::Display for Mismatch {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self.expected {
+            Some(expected) => write!(
+                f,
+                "prob-{:03}: score: expected {}, got {} ({:+})",
+                self.id,
+                expected,
+                self.actual,
+                self.actual as i64 - expected as i64
+            )?,
+            None => write!(
+                f,
+                "prob-{:03}: score: not blessed yet, got {}",
+                self.id, self.actual
+            )?,
+        }
+        if let Some(diff) = &self.solution_diff {
+            write!(f, "\n{}", diff)?;
+        }
+        Ok(())
+    }
+}
+
+fn parse_scores(s: &str) -> Result<BTreeMap<TaskId, Option<usize>>> {
+    s.lines()
+        .map(|line| line.trim())
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(
+            |line| match line.split_whitespace().collect::<Vec<_>>()[..] {
+                [id, "-"] => Ok((id.parse()?, None)),
+                [id, score] => Ok((id.parse()?, Some(score.parse()?))),
+                _ => Err(failure::format_err!("bad score: {}", line)),
+            },
+        )
+        .collect()
+}
+
+fn read_scores(dir: &Path) -> Result<BTreeMap<TaskId, Option<usize>>> {
+    let file = dir.join("scores.txt");
+    Ok(parse_scores(&std::fs::read_to_string(&file)?)
+        .with_context(|_| file.display().to_string())?)
+}
+
+// Describes where two solutions first differ, bot by bot.
+fn diff_solutions(expected: &str, actual: &str) -> Option<String> {
+    if expected == actual {
+        return None;
+    }
+    const CONTEXT: usize = 20;
+    let excerpt = |s: &[char], at: usize| {
+        let start = at.saturating_sub(CONTEXT);
+        let end = (at + CONTEXT).min(s.len());
+        format!(
+            "{}{}{}",
+            if start > 0 { "..." } else { "" },
+            s[start..end].iter().collect::<String>(),
+            if end < s.len() { "..." } else { "" }
+        )
+    };
+    let (expected, actual) = (
+        expected.split('#').collect::<Vec<_>>(),
+        actual.split('#').collect::<Vec<_>>(),
+    );
+    let mut lines = vec![];
+    if expected.len() != actual.len() {
+        lines.push(format!(
+            "  bots: expected {}, got {}",
+            expected.len(),
+            actual.len()
+        ));
+    }
+    for (bot, (a, b)) in expected.iter().zip(&actual).enumerate() {
+        let (a, b) = (a.chars().collect::<Vec<_>>(), b.chars().collect::<Vec<_>>());
+        if let Some(at) = (0..a.len().max(b.len())).find(|i| a.get(*i) != b.get(*i)) {
+            lines.push(format!(
+                "  bot {}: differs at character {}\n    expected: {}\n    actual:   {}",
+                bot,
+                at,
+                excerpt(&a, at),
+                excerpt(&b, at)
+            ));
+        }
+    }
+    Some(lines.join("\n"))
+}
+
+// Runs the default solver on every problem of the snapshot, and returns those which differ.
+pub fn check(dir: &Path) -> Result<Vec<Mismatch>> {
+    let available = Task::ids()?;
+    let mut mismatches = vec![];
+    for (id, expected) in read_scores(dir)? {
+        if !available.contains(&id) {
+            warn!("prob-{:03}: skipped, not in contest/problem", id);
+            continue;
+        }
+        let solution = solve(id, &RunOptions::default())?;
+        let sol_file = dir.join(format!("prob-{:03}.sol", id));
+        let solution_diff = if sol_file.exists() {
+            diff_solutions(
+                std::fs::read_to_string(&sol_file)?.trim(),
+                &solution.solution,
+            )
+        } else {
+            None
+        };
+        if expected != Some(solution.score) || solution_diff.is_some() {
+            mismatches.push(Mismatch {
+                id,
+                expected,
+                actual: solution.score,
+                solution_diff,
+            });
+        }
+    }
+    Ok(mismatches)
+}
+
+// Records the current output of the default solver as the expected one. With no ids, the problems
+// already in the snapshot are updated.
+pub fn bless(dir: &Path, ids: &[TaskId]) -> Result<()> {
+    std::fs::create_dir_all(dir)?;
+    let mut scores = if dir.join("scores.txt").exists() {
+        read_scores(dir)?
+    } else {
+        BTreeMap::new()
+    };
+    let ids = if ids.is_empty() {
+        let available = Task::ids()?;
+        scores
+            .keys()
+            .cloned()
+            .filter(|id| available.contains(id))
+            .collect()
+    } else {
+        ids.to_vec()
+    };
+    for id in ids {
+        let solution = solve(id, &RunOptions::default())?;
+        println!("prob-{:03}: {}", id, solution.score);
+        std::fs::write(dir.join(format!("prob-{:03}.sol", id)), &solution.solution)?;
+        scores.insert(id, Some(solution.score));
+    }
+    let mut s = "# Turns of the default solver. Update with `snapshot --bless`.\n".to_string();
+    for (id, score) in scores {
+        match score {
+            Some(score) => s += &format!("{} {}\n", id, score),
+            None => s += &format!("{} -\n", id),
+        }
+    }
+    std::fs::write(dir.join("scores.txt"), s)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn snapshot_test() -> Result<()> {
+        let mismatches = check(&snapshot_dir())?;
+        assert!(
+            mismatches.is_empty(),
+            "the solver output changed; run `cargo run --release -- snapshot --bless` if this \
+             is intended:\n{}",
+            mismatches
+                .iter()
+                .map(|mismatch| mismatch.to_string())
+                .collect::<Vec<_>>()
+                .join("\n")
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn parse_scores_test() -> Result<()> {
+        let scores = parse_scores("# id turns\n1 13\n\n21 -\n")?;
+        assert_eq!(scores[&1], Some(13));
+        assert_eq!(scores[&21], None);
+        assert!(parse_scores("1").is_err());
+        assert!(parse_scores("1 x").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn diff_solutions_test() {
+        assert_eq!(diff_solutions("WD#A", "WD#A"), None);
+        assert_eq!(
+            diff_solutions("WDDS#A", "WDS#A").unwrap(),
+            "  bot 0: differs at character 2\n    expected: WDDS\n    actual:   WDS"
+        );
+        assert!(diff_solutions("W", "W#A")
+            .unwrap()
+            .starts_with("  bots: expected 1, got 2"));
+    }
+}
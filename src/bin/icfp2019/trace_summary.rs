@@ -0,0 +1,184 @@
+use std::collections::BTreeMap;
+
+use crate::prelude::*;
+
+#[derive(Debug, Default, PartialEq)]
+pub struct TraceSummary {
+    pub turns: usize,
+    pub actions: usize,
+    // Turns a bot did nothing, or had nothing more to do before the end.
+    pub idle: usize,
+    // Moves which painted no cell.
+    pub walking_over_painted: usize,
+    // Turns between picking up a booster and using one of the same kind, per kind.
+    pub pickup_latency: BTreeMap<String, Vec<usize>>,
+}
+
+pub fn summarize(events: &[TraceEvent]) -> TraceSummary {
+    let mut summary = TraceSummary {
+        turns: events.iter().map(|event| event.turn).max().unwrap_or(0),
+        actions: events.len(),
+        ..Default::default()
+    };
+
+    let mut last_turn = BTreeMap::new();
+    // Boosters are shared, so the oldest one picked up is the one used.
+    let mut picked: BTreeMap<String, VecDeque<usize>> = BTreeMap::new();
+    for event in events {
+        last_turn.insert(event.bot, event.turn);
+        let kind = match event.action.chars().next() {
+            Some('Z') => {
+                summary.idle += 1;
+                None
+            }
+            Some('W') | Some('S') | Some('A') | Some('D') => {
+                if event.painted == 0 {
+                    summary.walking_over_painted += 1;
+                }
+                None
+            }
+            Some(c @ 'B') | Some(c @ 'F') | Some(c @ 'L') | Some(c @ 'C') => Some(c.to_string()),
+            _ => None,
+        };
+        if let Some(kind) = kind {
+            if let Some(turn) = picked.get_mut(&kind).and_then(|turns| turns.pop_front()) {
+                summary
+                    .pickup_latency
+                    .entry(kind)
+                    .or_default()
+                    .push(event.turn - turn);
+            }
+        }
+        for kind in &event.picked {
+            picked
+                .entry(kind.clone())
+                .or_default()
+                .push_back(event.turn);
+        }
+    }
+    summary.idle += last_turn
+        .values()
+        .map(|turn| summary.turns - turn)
+        .sum::<usize>();
+    summary
+}
+
+impl TraceSummary {
+    // Adds up the summaries of several runs.
+    pub fn add(&mut self, other: &TraceSummary) {
+        self.turns += other.turns;
+        self.actions += other.actions;
+        self.idle += other.idle;
+        self.walking_over_painted += other.walking_over_painted;
+        for (kind, latency) in &other.pickup_latency {
+            self.pickup_latency
+                .entry(kind.clone())
+                .or_default()
+                .extend(latency);
+        }
+    }
+}
+
+// The .trace.jsonl files among the paths, looking into directories.
+pub fn trace_files(paths: &[PathBuf]) -> Result<Vec<PathBuf>> {
+    let mut files = vec![];
+    for path in paths {
+        if path.is_dir() {
+            let mut found = std::fs::read_dir(path)?
+                .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+                .filter(|path| {
+                    path.to_str()
+                        .is_some_and(|name| name.ends_with(".trace.jsonl"))
+                })
+                .collect::<Vec<_>>();
+            found.sort();
+            files.extend(found);
+        } else {
+            files.push(path.clone());
+        }
+    }
+    Ok(files)
+}
+
+// Prints the summary of each trace file, and of all of them.
+pub fn trace_summary(paths: &[PathBuf]) -> Result<TraceSummary> {
+    let mut total = TraceSummary::default();
+    for file in trace_files(paths)? {
+        let events = parse_json_lines(&std::fs::read_to_string(&file)?)
+            .with_context(|_| file.display().to_string())?;
+        let summary = summarize(&events);
+        println!("{}:\n{}", file.display(), summary);
+        total.add(&summary);
+    }
+    println!("total:\n{}", total);
+    Ok(total)
+}
+
+impl std::fmt::Display for TraceSummary {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        writeln!(f, "turns: {}, actions: {}", self.turns, self.actions)?;
+        writeln!(f, "idle turns: {}", self.idle)?;
+        write!(f, "moves over painted cells: {}", self.walking_over_painted)?;
+        for (kind, latency) in &self.pickup_latency {
+            write!(
+                f,
+                "\nbooster {}: used {}, pickup latency avg: {:.1}, max: {}",
+                kind,
+                latency.len(),
+                latency.iter().sum::<usize>() as f64 / latency.len() as f64,
+                latency.iter().max().unwrap()
+            )?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn trace_test() -> Result<()> {
+        let mut system = System::new(2)?;
+        system.enable_trace();
+        system.solve()?;
+        let solution = system.solution();
+        let events = parse_json_lines(solution.trace.as_ref().unwrap())?;
+        let actions = parse_solution(&solution.solution)?;
+        assert_eq!(events.len(), actions.iter().map(Vec::len).sum::<usize>());
+        assert_eq!(events.last().unwrap().empty, 0);
+
+        let summary = summarize(&events);
+        assert_eq!(summary.turns, solution.score);
+        assert_eq!(summary.actions, events.len());
+        Ok(())
+    }
+
+    #[test]
+    fn summarize_test() {
+        let event = |turn, bot, action: &str, painted, picked: &[&str]| TraceEvent {
+            turn,
+            bot,
+            x: 0,
+            y: 0,
+            angle: 0,
+            action: action.to_string(),
+            painted,
+            plan: None,
+            fast_wheel_timer: 0,
+            drill_timer: 0,
+            empty: 0,
+            picked: picked.iter().map(|kind| kind.to_string()).collect(),
+        };
+        let summary = summarize(&[
+            event(1, 0, "D", 2, &["F"]),
+            event(2, 0, "A", 0, &[]),
+            event(3, 0, "F", 0, &[]),
+            event(4, 0, "Z", 0, &[]),
+            event(5, 0, "D", 1, &[]),
+        ]);
+        assert_eq!(summary.idle, 1);
+        assert_eq!(summary.walking_over_painted, 1);
+        assert_eq!(summary.pickup_latency["F"], vec![2]);
+    }
+}
@@ -0,0 +1,122 @@
+use std::collections::BTreeMap;
+
+use crate::task::*;
+
+// A booster on the map which a bot is on its way to pick up, so that no other bot goes for it.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub(crate) struct Reservation {
+    pub(crate) bot: usize,
+    pub(crate) kind: BoosterKind,
+    // The turn the bot is expected to get there.
+    pub(crate) eta: usize,
+}
+
+#[derive(Debug, Clone, PartialEq, Default)]
+pub(crate) struct Reservations {
+    table: BTreeMap<Pos, Reservation>,
+}
+
+impl Reservations {
+    pub(crate) fn reserve(&mut self, pos: Pos, reservation: Reservation) {
+        let prev = self.table.insert(pos, reservation);
+        assert!(prev.is_none(), "booster at {:?} is reserved twice", pos);
+    }
+
+    pub(crate) fn release(&mut self, pos: Pos) -> Option<Reservation> {
+        self.table.remove(&pos)
+    }
+
+    pub(crate) fn get_mut(&mut self, pos: Pos) -> Option<&mut Reservation> {
+        self.table.get_mut(&pos)
+    }
+
+    pub(crate) fn is_reserved(&self, pos: Pos) -> bool {
+        self.table.contains_key(&pos)
+    }
+
+    pub(crate) fn iter(&self) -> impl Iterator<Item = (Pos, Reservation)> + '_ {
+        self.table
+            .iter()
+            .map(|(pos, reservation)| (*pos, *reservation))
+    }
+}
+
+// Boosters the bots picked up or bought, shared by all of them. A booster picked up in a turn
+// can be used from the next turn.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub(crate) struct Inventory {
+    // Each booster with the turn it can be used from, sorted.
+    boosters: Vec<(BoosterKind, usize)>,
+}
+
+impl Inventory {
+    pub(crate) fn add(&mut self, kind: BoosterKind, usable_from: usize) {
+        let index = match self.boosters.binary_search(&(kind, usable_from)) {
+            Ok(index) | Err(index) => index,
+        };
+        self.boosters.insert(index, (kind, usable_from));
+    }
+
+    // How many of the kind can be used at the turn.
+    pub(crate) fn count(&self, kind: BoosterKind, turn: usize) -> usize {
+        self.boosters
+            .iter()
+            .filter(|(k, usable_from)| *k == kind && *usable_from <= turn)
+            .count()
+    }
+
+    // How many of the kind there are, whether usable yet or not.
+    pub(crate) fn held(&self, kind: BoosterKind) -> usize {
+        self.count(kind, usize::MAX)
+    }
+
+    // Takes the oldest one of the kind which can be used at the turn, and returns the turn it
+    // was usable from, to put it back with `add`.
+    pub(crate) fn take(&mut self, kind: BoosterKind, turn: usize) -> Option<usize> {
+        let index = self
+            .boosters
+            .iter()
+            .position(|(k, usable_from)| *k == kind && *usable_from <= turn)?;
+        Some(self.boosters.remove(index).1)
+    }
+
+    // Takes back one added with `add`.
+    pub(crate) fn remove(&mut self, kind: BoosterKind, usable_from: usize) {
+        let index = self.boosters.binary_search(&(kind, usable_from)).unwrap();
+        self.boosters.remove(index);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn reservations_test() {
+        let mut reservations = Reservations::default();
+        let reservation = Reservation {
+            bot: 1,
+            kind: BoosterKind::FastWheels,
+            eta: 10,
+        };
+        reservations.reserve(Pos::new(2, 3), reservation);
+        assert!(reservations.is_reserved(Pos::new(2, 3)));
+        assert!(!reservations.is_reserved(Pos::new(3, 2)));
+        reservations.get_mut(Pos::new(2, 3)).unwrap().bot = 0;
+        assert_eq!(reservations.iter().next().unwrap().1.bot, 0);
+        assert_eq!(reservations.release(Pos::new(2, 3)).unwrap().eta, 10);
+        assert_eq!(reservations.iter().count(), 0);
+
+        let mut inventory = Inventory::default();
+        assert_eq!(inventory.take(BoosterKind::Cloning, 0), None);
+        inventory.add(BoosterKind::Cloning, 5);
+        inventory.add(BoosterKind::Cloning, 3);
+        assert_eq!(inventory.count(BoosterKind::Cloning, 2), 0);
+        assert_eq!(inventory.count(BoosterKind::Cloning, 3), 1);
+        assert_eq!(inventory.count(BoosterKind::Cloning, 5), 2);
+        assert_eq!(inventory.take(BoosterKind::Cloning, 4), Some(3));
+        assert_eq!(inventory.take(BoosterKind::Cloning, 4), None);
+        inventory.remove(BoosterKind::Cloning, 5);
+        assert_eq!(inventory.count(BoosterKind::Cloning, 5), 0);
+    }
+}
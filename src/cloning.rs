@@ -0,0 +1,338 @@
+use crate::grid::*;
+use crate::prelude::*;
+use crate::system::*;
+use crate::task::*;
+
+// Up to this many clone boosters, every order of fetching them is tried.
+const EXHAUSTIVE_LIMIT: usize = 6;
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub(crate) struct CloneTask {
+    // Tells apart the bot the task spawns.
+    id: usize,
+    pub(crate) booster: Pos,
+    pub(crate) spawn: Pos,
+}
+
+// Who does clone tasks: a bot which exists when planning, or the bot spawned by a task. Bots are
+// numbered as they happen to be spawned, which need not be the planned order.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+enum Worker {
+    Bot(usize),
+    SpawnedBy(usize),
+}
+
+// Clone tasks of each bot, in the order the bot should do them.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct ClonePlan {
+    tasks: HashMap<Worker, VecDeque<CloneTask>>,
+    // How many bots there were when planning.
+    bots: usize,
+    // The task each bot was last given, and the task which spawned each new bot.
+    doing: HashMap<usize, usize>,
+    spawned: HashMap<usize, usize>,
+    // Estimated turn at which the last bot is done with cloning, which bounds when the map can be
+    // done, and then the sum over all bots of it to break ties.
+    cost: (usize, usize),
+}
+
+impl ClonePlan {
+    fn worker(&self, bot: usize) -> Option<Worker> {
+        match self.spawned.get(&bot) {
+            Some(id) => Some(Worker::SpawnedBy(*id)),
+            None if bot < self.bots => Some(Worker::Bot(bot)),
+            None => None,
+        }
+    }
+
+    pub(crate) fn next_task(&mut self, bot: usize) -> Option<CloneTask> {
+        let worker = self.worker(bot)?;
+        // The bot gave up its last task without cloning, so it does the tasks of the bot it
+        // would have spawned as well.
+        if let Some(id) = self.doing.remove(&bot) {
+            if let Some(orphaned) = self.tasks.remove(&Worker::SpawnedBy(id)) {
+                self.tasks.entry(worker).or_default().extend(orphaned);
+            }
+        }
+        let task = self.tasks.get_mut(&worker)?.pop_front()?;
+        self.doing.insert(bot, task.id);
+        Some(task)
+    }
+
+    // To be called when the bot clones, with the index of the new bot.
+    pub(crate) fn spawned(&mut self, bot: usize, clone: usize) {
+        if let Some(id) = self.doing.remove(&bot) {
+            self.spawned.insert(clone, id);
+        }
+    }
+
+    pub(crate) fn is_empty(&self) -> bool {
+        self.tasks.values().all(|tasks| tasks.is_empty())
+    }
+}
+
+// Walking distance from `from` to every cell, without fast wheels or drills.
+pub(crate) fn distances(map: &Map, from: Pos) -> Grid<usize> {
+    let mut dist = Grid::new(map.max_x, map.max_y, usize::MAX);
+    dist[from] = 0;
+    let mut q = VecDeque::new();
+    q.push_back(from);
+    while let Some(pos) = q.pop_front() {
+        let len = dist[pos];
+        for next in map.walls.neighbours(pos) {
+            if map.is_free(next) && dist[next] == usize::MAX {
+                dist[next] = len + 1;
+                q.push_back(next);
+            }
+        }
+    }
+    dist
+}
+
+struct Planner {
+    boosters: Vec<Pos>,
+    spawns: Vec<Pos>,
+    // Distances from each clone booster.
+    booster_dist: Vec<Grid<usize>>,
+    // (pos, turn at which the bot is free) of the existing bots.
+    bots: Vec<(Pos, usize)>,
+}
+
+impl Planner {
+    // Assigns the boosters in the given order, each to the bot and the spawn point which finish
+    // cloning first. A bot spends dist(bot, booster) + dist(booster, spawn) turns walking and one
+    // turn cloning. A booster is usable from the turn after it is picked up, which the cloning
+    // turn already covers.
+    fn schedule(&self, order: &[usize]) -> ClonePlan {
+        let mut bots = self
+            .bots
+            .iter()
+            .enumerate()
+            .map(|(i, (pos, ready))| (Worker::Bot(i), *pos, *ready))
+            .collect::<Vec<_>>();
+        let mut tasks: HashMap<Worker, VecDeque<CloneTask>> = HashMap::new();
+        for (id, &b) in order.iter().enumerate() {
+            let dist = &self.booster_dist[b];
+            let mut best: Option<(usize, usize, Pos)> = None;
+            for (i, (_, pos, ready)) in bots.iter().enumerate() {
+                if dist[*pos] == usize::MAX {
+                    continue;
+                }
+                for spawn in &self.spawns {
+                    if dist[*spawn] == usize::MAX {
+                        continue;
+                    }
+                    let finish = ready + dist[*pos] + dist[*spawn] + 1;
+                    if best.is_none_or(|(best_finish, ..)| finish < best_finish) {
+                        best = Some((finish, i, *spawn));
+                    }
+                }
+            }
+            if let Some((finish, i, spawn)) = best {
+                tasks.entry(bots[i].0).or_default().push_back(CloneTask {
+                    id,
+                    booster: self.boosters[b],
+                    spawn,
+                });
+                bots[i].1 = spawn;
+                bots[i].2 = finish;
+                bots.push((Worker::SpawnedBy(id), spawn, finish));
+            }
+        }
+
+        ClonePlan {
+            tasks,
+            bots: self.bots.len(),
+            doing: HashMap::new(),
+            spawned: HashMap::new(),
+            cost: (
+                bots.iter().map(|(.., ready)| *ready).max().unwrap(),
+                bots.iter().map(|(.., ready)| ready).sum(),
+            ),
+        }
+    }
+
+    // Earliest finishing booster first.
+    fn greedy_order(&self) -> Vec<usize> {
+        let mut order = vec![];
+        let mut remaining = (0..self.boosters.len()).collect::<Vec<_>>();
+        while !remaining.is_empty() {
+            let (index, _) = remaining
+                .iter()
+                .enumerate()
+                .min_by_key(|(_, b)| {
+                    let mut candidate = order.clone();
+                    candidate.push(**b);
+                    self.schedule(&candidate).cost
+                })
+                .unwrap();
+            order.push(remaining.remove(index));
+        }
+        order
+    }
+
+    fn best_permutation(&self, order: &mut Vec<usize>, rest: &mut Vec<usize>) -> ClonePlan {
+        if rest.is_empty() {
+            return self.schedule(order);
+        }
+        let mut best: Option<ClonePlan> = None;
+        for i in 0..rest.len() {
+            let b = rest.remove(i);
+            order.push(b);
+            let plan = self.best_permutation(order, rest);
+            if best.as_ref().is_none_or(|best| plan.cost < best.cost) {
+                best = Some(plan);
+            }
+            order.pop();
+            rest.insert(i, b);
+        }
+        best.unwrap()
+    }
+}
+
+// Plans which bot fetches which clone booster and at which mysterious point it clones, so that
+// the bots are done with cloning as early as possible in total. None if there is nothing to
+// clone.
+pub(crate) fn plan(system: &System) -> Option<ClonePlan> {
+    let boosters = system
+        .booster_pos
+        .iter()
+        .filter(|(_, kind)| **kind == Some(BoosterKind::Cloning))
+        .map(|(pos, _)| pos)
+        .collect::<Vec<_>>();
+    if boosters.is_empty() || system.mysterious_pos.count() == 0 {
+        return None;
+    }
+    let planner = Planner {
+        booster_dist: boosters
+            .iter()
+            .map(|pos| distances(&system.map, *pos))
+            .collect(),
+        boosters,
+        spawns: system.mysterious_pos.iter().collect(),
+        bots: system
+            .bots
+            .iter()
+            .map(|bot| (bot.pos_angle.pos, bot.time()))
+            .collect(),
+    };
+
+    let plan = if planner.boosters.len() <= EXHAUSTIVE_LIMIT {
+        let mut rest = (0..planner.boosters.len()).collect();
+        planner.best_permutation(&mut vec![], &mut rest)
+    } else {
+        planner.schedule(&planner.greedy_order())
+    };
+    if plan.is_empty() {
+        None
+    } else {
+        Some(plan)
+    }
+}
+
+// A greedy run, and the clone plan it followed if any.
+pub(crate) struct ClonedRun {
+    pub(crate) plan: Option<ClonePlan>,
+    pub(crate) solved: System,
+}
+
+impl System {
+    // Solves greedily with a clone plan and without, and returns the run which finishes earlier.
+    // Without a plan, bots fetch clone boosters and go to the nearest mysterious point.
+    pub(crate) fn plan_cloning(&self) -> Result<ClonedRun> {
+        let mut without_plan = self.clone();
+        without_plan.solve()?;
+        let plan = match plan(self) {
+            Some(plan) => plan,
+            None => {
+                return Ok(ClonedRun {
+                    plan: None,
+                    solved: without_plan,
+                })
+            }
+        };
+        let mut with_plan = self.clone();
+        with_plan.clone_plan = Some(plan.clone());
+        with_plan.solve()?;
+        info!(
+            "clone plan: {} turns without, {} turns with the plan",
+            without_plan.turns(),
+            with_plan.turns()
+        );
+        Ok(if with_plan.turns() < without_plan.turns() {
+            ClonedRun {
+                plan: Some(plan),
+                solved: with_plan,
+            }
+        } else {
+            ClonedRun {
+                plan: None,
+                solved: without_plan,
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn clone_plan_test() -> Result<()> {
+        // Three clone boosters and two mysterious points.
+        let system = System::new(6)?;
+        let plan = plan(&system).unwrap();
+        let mut boosters = vec![];
+        for task in plan.tasks.values().flatten() {
+            assert!(system.mysterious_pos.contains(task.spawn));
+            boosters.push(task.booster);
+        }
+        boosters.sort_by_key(|pos| (pos.x, pos.y));
+        boosters.dedup();
+        assert_eq!(boosters.len(), 3);
+
+        let system = System::new(2)?;
+        let cloned = system.plan_cloning()?;
+        assert!(cloned.solved.is_solved());
+        // The plan gives the same run again.
+        let mut again = system.clone();
+        again.clone_plan = cloned.plan;
+        again.solve()?;
+        assert_eq!(again.solution().solution, cloned.solved.solution().solution);
+        Ok(())
+    }
+
+    #[test]
+    fn spawned_test() {
+        let task = |id| CloneTask {
+            id,
+            booster: Pos::new(id as i32, 0),
+            spawn: Pos::new(0, 0),
+        };
+        let mut tasks = HashMap::new();
+        tasks.insert(Worker::Bot(0), vec![task(0)].into());
+        tasks.insert(Worker::SpawnedBy(0), vec![task(1)].into());
+        let plan = ClonePlan {
+            tasks,
+            bots: 2,
+            doing: HashMap::new(),
+            spawned: HashMap::new(),
+            cost: (0, 0),
+        };
+
+        // The tasks of a new bot go to the one the task spawned, whichever index it gets.
+        let mut spawning = plan.clone();
+        assert_eq!(spawning.next_task(0), Some(task(0)));
+        assert_eq!(spawning.next_task(1), None);
+        spawning.spawned(1, 2);
+        spawning.spawned(0, 3);
+        assert_eq!(spawning.next_task(2), None);
+        assert_eq!(spawning.next_task(3), Some(task(1)));
+        assert!(spawning.is_empty());
+
+        // A bot which gives up a task does the tasks of the bot it would have spawned.
+        let mut giving_up = plan;
+        assert_eq!(giving_up.next_task(0), Some(task(0)));
+        assert_eq!(giving_up.next_task(0), Some(task(1)));
+    }
+}
@@ -0,0 +1,170 @@
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::{Rng, SeedableRng};
+
+use crate::grid::*;
+use crate::prelude::*;
+use crate::puzzle::Shape;
+use crate::task::*;
+
+#[derive(Debug, Clone)]
+pub struct GenerateOptions {
+    // The map fits in a size x size square.
+    pub size: i32,
+    // How many cells are eroded from the border of the square, which makes the outline ragged.
+    pub erosion: usize,
+    // Rectangular obstacles, which are kept apart from each other and from the border.
+    pub obstacles: usize,
+    // Boosters of each kind.
+    pub boosters: usize,
+    pub seed: u64,
+}
+
+impl Default for GenerateOptions {
+    fn default() -> GenerateOptions {
+        GenerateOptions {
+            size: 30,
+            erosion: 100,
+            obstacles: 5,
+            boosters: 1,
+            seed: 0,
+        }
+    }
+}
+
+// A random task: a simple rectilinear map, with obstacles, boosters of every kind and the bot on
+// distinct free cells. The same options give the same task.
+pub fn generate(id: TaskId, options: &GenerateOptions) -> Result<Task> {
+    let size = options.size;
+    if size < 3 {
+        return Err(failure::format_err!("too small a map: {}", size));
+    }
+    let mut rng = StdRng::seed_from_u64(options.seed);
+
+    let mut shape = Shape::new(size, &[]);
+    // Keep at least half of the square, so that there is room for the rest.
+    let mut left = options.erosion.min((size * size / 2) as usize);
+    let mut failures = 0;
+    while left > 0 && failures < 100 {
+        let border = shape
+            .inside
+            .positions()
+            .filter(|pos| shape.is_border(*pos))
+            .collect::<Vec<_>>();
+        let pos = *border.choose(&mut rng).unwrap();
+        if shape.can_remove(pos, &[]) {
+            shape.inside[pos] = false;
+            left -= 1;
+            failures = 0;
+        } else {
+            failures += 1;
+        }
+    }
+
+    // An obstacle and the ring of cells around it must be inside the map and clear of the other
+    // obstacles, so that the free cells stay connected.
+    let mut blocked = BitGrid::new(size, size);
+    let mut obstacles = vec![];
+    let max_side = (size / 6).max(1);
+    for _ in 0..options.obstacles * 20 {
+        if obstacles.len() == options.obstacles {
+            break;
+        }
+        let (w, h) = (
+            rng.gen_range(1, max_side + 1),
+            rng.gen_range(1, max_side + 1),
+        );
+        let (x, y) = (rng.gen_range(1, size), rng.gen_range(1, size));
+        let ring = (x - 1..=x + w).flat_map(|x| (y - 1..=y + h).map(move |y| Pos::new(x, y)));
+        if !ring
+            .clone()
+            .all(|pos| shape.contains(pos) && !blocked.contains(pos))
+        {
+            continue;
+        }
+        for pos in (x..x + w).flat_map(|x| (y..y + h).map(move |y| Pos::new(x, y))) {
+            blocked.insert(pos);
+        }
+        obstacles.push(vec![
+            Pos::new(x, y),
+            Pos::new(x + w, y),
+            Pos::new(x + w, y + h),
+            Pos::new(x, y + h),
+        ]);
+    }
+
+    use BoosterKind::*;
+    let kinds = [
+        ExtendManipulator,
+        FastWheels,
+        Drill,
+        Mysterious,
+        Teleport,
+        Cloning,
+    ]
+    .iter()
+    .flat_map(|kind| std::iter::repeat_n(*kind, options.boosters))
+    .collect::<Vec<_>>();
+    let mut free = shape
+        .inside
+        .positions()
+        .filter(|pos| shape.contains(*pos) && !blocked.contains(*pos))
+        .collect::<Vec<_>>();
+    if free.len() < kinds.len() + 1 {
+        return Err(failure::err_msg("too many boosters for the map"));
+    }
+    free.shuffle(&mut rng);
+
+    Task::new(
+        id,
+        shape.tour(),
+        free[0],
+        obstacles,
+        kinds
+            .into_iter()
+            .zip(&free[1..])
+            .map(|(kind, pos)| Booster { pos: *pos, kind })
+            .collect(),
+    )
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::system::*;
+
+    #[test]
+    fn generate_test() -> Result<()> {
+        for seed in 0..5 {
+            let options = GenerateOptions {
+                seed,
+                ..GenerateOptions::default()
+            };
+            let task = generate(0, &options)?;
+            assert_eq!(task.to_string(), generate(0, &options)?.to_string());
+            assert_eq!(task.boosters().len(), 6);
+
+            // Every free cell can be reached from the bot.
+            let map = Map::new(Task::parse(0, &task.to_string())?);
+            let mut seen = BitGrid::new(map.max_x, map.max_y);
+            let mut queue = VecDeque::new();
+            seen.insert(task.bot());
+            queue.push_back(task.bot());
+            while let Some(pos) = queue.pop_front() {
+                for diff in &[(0, 1), (1, 0), (0, -1), (-1, 0)] {
+                    let next = Pos::new(pos.x + diff.0, pos.y + diff.1);
+                    if map.is_in_range(next) && !map.is_wall(next) && !seen.contains(next) {
+                        seen.insert(next);
+                        queue.push_back(next);
+                    }
+                }
+            }
+            assert_eq!(seen.count(), map.empty_cell_count);
+
+            let mut system = System::from_task(task);
+            system.solve()?;
+            assert!(system.is_solved());
+        }
+        Ok(())
+    }
+}
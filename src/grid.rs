@@ -0,0 +1,238 @@
+use crate::task::*;
+
+// Cells are stored row by row (y major) in a single buffer.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Grid<T> {
+    width: i32,
+    height: i32,
+    cells: Vec<T>,
+}
+
+impl<T: Clone> Grid<T> {
+    pub fn new(width: i32, height: i32, init: T) -> Grid<T> {
+        assert!(width >= 0 && height >= 0);
+        Grid {
+            width,
+            height,
+            cells: vec![init; (width * height) as usize],
+        }
+    }
+
+    pub fn fill(&mut self, value: T) {
+        for cell in &mut self.cells {
+            *cell = value.clone();
+        }
+    }
+}
+
+impl<T> Grid<T> {
+    pub fn width(&self) -> i32 {
+        self.width
+    }
+
+    pub fn height(&self) -> i32 {
+        self.height
+    }
+
+    pub fn is_in_range(&self, pos: Pos) -> bool {
+        0 <= pos.x && pos.x < self.width && 0 <= pos.y && pos.y < self.height
+    }
+
+    fn index_of(&self, pos: Pos) -> usize {
+        assert!(
+            self.is_in_range(pos),
+            "pos {:?} is out of grid {}x{}",
+            pos,
+            self.width,
+            self.height
+        );
+        self.offset(pos)
+    }
+
+    // The index of an in-range position.
+    fn offset(&self, pos: Pos) -> usize {
+        (pos.y * self.width + pos.x) as usize
+    }
+
+    pub fn get(&self, pos: Pos) -> Option<&T> {
+        if self.is_in_range(pos) {
+            Some(&self.cells[self.offset(pos)])
+        } else {
+            None
+        }
+    }
+
+    pub fn get_mut(&mut self, pos: Pos) -> Option<&mut T> {
+        if self.is_in_range(pos) {
+            let index = self.offset(pos);
+            Some(&mut self.cells[index])
+        } else {
+            None
+        }
+    }
+
+    pub fn positions(&self) -> impl Iterator<Item = Pos> {
+        let width = self.width;
+        (0..self.height).flat_map(move |y| (0..width).map(move |x| Pos::new(x, y)))
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (Pos, &T)> {
+        self.positions().zip(self.cells.iter())
+    }
+
+    // In-range 4-neighbours, in the order of PosDiff::gen_all_diff.
+    pub fn neighbours(&self, pos: Pos) -> impl Iterator<Item = Pos> {
+        let (width, height) = (self.width, self.height);
+        PosDiff::gen_all_diff()
+            .iter()
+            .map(move |diff| pos + *diff)
+            .filter(move |p| 0 <= p.x && p.x < width && 0 <= p.y && p.y < height)
+    }
+}
+
+impl<T> std::ops::Index<Pos> for Grid<T> {
+    type Output = T;
+    fn index(&self, pos: Pos) -> &T {
+        &self.cells[self.index_of(pos)]
+    }
+}
+
+impl<T> std::ops::IndexMut<Pos> for Grid<T> {
+    fn index_mut(&mut self, pos: Pos) -> &mut T {
+        let index = self.index_of(pos);
+        &mut self.cells[index]
+    }
+}
+
+// Boolean layer over a grid, packed 64 cells per word. Out-of-range reads are false.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct BitGrid {
+    width: i32,
+    height: i32,
+    bits: Vec<u64>,
+}
+
+impl BitGrid {
+    pub fn new(width: i32, height: i32) -> BitGrid {
+        assert!(width >= 0 && height >= 0);
+        BitGrid {
+            width,
+            height,
+            bits: vec![0; ((width * height) as usize).div_ceil(64)],
+        }
+    }
+
+    pub fn width(&self) -> i32 {
+        self.width
+    }
+
+    pub fn height(&self) -> i32 {
+        self.height
+    }
+
+    pub fn is_in_range(&self, pos: Pos) -> bool {
+        0 <= pos.x && pos.x < self.width && 0 <= pos.y && pos.y < self.height
+    }
+
+    fn index_of(&self, pos: Pos) -> (usize, u64) {
+        let index = (pos.y * self.width + pos.x) as usize;
+        (index / 64, 1 << (index % 64))
+    }
+
+    pub fn contains(&self, pos: Pos) -> bool {
+        if !self.is_in_range(pos) {
+            return false;
+        }
+        let (word, mask) = self.index_of(pos);
+        self.bits[word] & mask != 0
+    }
+
+    // Returns true if the bit was newly set, like HashSet::insert.
+    pub fn insert(&mut self, pos: Pos) -> bool {
+        assert!(self.is_in_range(pos), "pos {:?} is out of grid", pos);
+        let (word, mask) = self.index_of(pos);
+        let inserted = self.bits[word] & mask == 0;
+        self.bits[word] |= mask;
+        inserted
+    }
+
+    pub fn remove(&mut self, pos: Pos) -> bool {
+        if !self.is_in_range(pos) {
+            return false;
+        }
+        let (word, mask) = self.index_of(pos);
+        let removed = self.bits[word] & mask != 0;
+        self.bits[word] &= !mask;
+        removed
+    }
+
+    pub fn clear(&mut self) {
+        for word in &mut self.bits {
+            *word = 0;
+        }
+    }
+
+    pub fn count(&self) -> usize {
+        self.bits
+            .iter()
+            .map(|word| word.count_ones() as usize)
+            .sum()
+    }
+
+    pub fn neighbours(&self, pos: Pos) -> impl Iterator<Item = Pos> {
+        let (width, height) = (self.width, self.height);
+        PosDiff::gen_all_diff()
+            .iter()
+            .map(move |diff| pos + *diff)
+            .filter(move |p| 0 <= p.x && p.x < width && 0 <= p.y && p.y < height)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = Pos> + '_ {
+        let width = self.width;
+        (0..self.height)
+            .flat_map(move |y| (0..width).map(move |x| Pos::new(x, y)))
+            .filter(move |pos| self.contains(*pos))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn grid_index_test() {
+        let mut grid = Grid::new(3, 2, 0);
+        grid[Pos::new(2, 1)] = 5;
+        assert_eq!(grid[Pos::new(2, 1)], 5);
+        assert_eq!(grid.get(Pos::new(3, 1)), None);
+        assert_eq!(grid.get(Pos::new(0, -1)), None);
+        assert_eq!(grid.iter().filter(|(_, v)| **v == 5).count(), 1);
+    }
+
+    #[test]
+    fn grid_neighbours_test() {
+        let grid = Grid::new(3, 3, ());
+        assert_eq!(
+            grid.neighbours(Pos::new(0, 0)).collect::<Vec<_>>(),
+            vec![Pos::new(1, 0), Pos::new(0, 1)]
+        );
+        assert_eq!(grid.neighbours(Pos::new(1, 1)).count(), 4);
+    }
+
+    #[test]
+    fn bit_grid_test() {
+        let mut bits = BitGrid::new(10, 10);
+        assert!(bits.insert(Pos::new(9, 9)));
+        assert!(!bits.insert(Pos::new(9, 9)));
+        assert!(bits.insert(Pos::new(0, 7)));
+        assert!(bits.contains(Pos::new(0, 7)));
+        assert!(!bits.contains(Pos::new(10, 0)));
+        assert_eq!(bits.count(), 2);
+        assert_eq!(
+            bits.iter().collect::<Vec<_>>(),
+            vec![Pos::new(0, 7), Pos::new(9, 9)]
+        );
+        assert!(bits.remove(Pos::new(9, 9)));
+        assert_eq!(bits.count(), 1);
+    }
+}
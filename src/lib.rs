@@ -1,8 +1,62 @@
-pub mod prelude;
-pub mod puzzle;
-pub mod run;
-pub mod system;
-pub mod task;
+//! A solver of the ICFP Programming Contest 2019 task: painting a map with wrapping bots.
+//!
+//! Load a task, then either run a solver on it:
+//!
+//! ```
+//! use icfp2019::{solve_task, RunOptions, Strategy, Task};
+//!
+//! # fn main() -> icfp2019::Result<()> {
+//! let task = Task::read_from(1, "contest/problem/prob-001.desc")?;
+//! let options = RunOptions {
+//!     strategy: Strategy::from_name("greedy")?,
+//!     ..RunOptions::default()
+//! };
+//! let solution = solve_task(task, &options)?;
+//! println!("{} turns: {}", solution.score, solution.solution);
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! or drive the simulator one action at a time:
+//!
+//! ```
+//! use icfp2019::{Action, System, Task};
+//!
+//! # fn main() -> icfp2019::Result<()> {
+//! let mut system = System::from_task(Task::read_from(1, "contest/problem/prob-001.desc")?);
+//! // Fails, instead of panicking, if the bot can not move up.
+//! system.try_step(0, Action::MoveUp)?;
+//! let bot = &system.bots()[0];
+//! println!("{:?}, {} left", bot.pos_angle(), system.map().empty_cell_count);
+//! # Ok(())
+//! # }
+//! ```
 
+mod beam;
+#[cfg(any(test, feature = "bench"))]
+pub mod bench;
+mod booster;
+mod cloning;
+mod generate;
+mod grid;
+mod plan;
+mod prelude;
+mod puzzle;
+mod replay;
+mod run;
+mod system;
+mod task;
+mod trace;
+mod visibility;
+
+pub use crate::beam::BeamOptions;
+pub use crate::generate::{generate, GenerateOptions};
+pub use crate::grid::{BitGrid, Grid};
+pub use crate::plan::{Goal, Plan, Region};
 pub use crate::prelude::Result;
-pub use crate::run::run;
+pub use crate::puzzle::Puzzle;
+pub use crate::replay::{parse_buy, parse_solution, replay, Replay};
+pub use crate::run::{solve_task, RunOptions, Strategy};
+pub use crate::system::{Action, Bot, Manipulator, PosAngle, Solution, StepDelta, System, Timeout};
+pub use crate::task::{Angle, Booster, BoosterKind, Map, Pos, PosDiff, Task, TaskId, Tour};
+pub use crate::trace::{parse_json_lines, TraceEvent};
@@ -0,0 +1,245 @@
+use crate::system::*;
+use crate::task::*;
+
+/// A step of what a bot of the greedy solver is going to do.
+#[derive(Debug, Copy, Clone, PartialEq, Hash)]
+pub enum Goal {
+    /// Stand on the cell.
+    GoTo(Pos),
+    /// Pass over the cell, picking up the booster lying there.
+    PickUp(Pos),
+    /// Use a booster of the kind from the inventory.
+    Activate(BoosterKind),
+    /// Install a teleport beacon on the cell.
+    PlaceBeacon(Pos),
+    /// Paint every cell of the region.
+    Sweep(Region),
+}
+
+/// The cells from `min` up to but not including `max`.
+#[derive(Debug, Copy, Clone, PartialEq, Hash)]
+pub struct Region {
+    pub min: Pos,
+    pub max: Pos,
+}
+
+impl Region {
+    pub fn new(min: Pos, max: Pos) -> Region {
+        Region { min, max }
+    }
+
+    pub fn contains(&self, pos: Pos) -> bool {
+        self.min.x <= pos.x && pos.x < self.max.x && self.min.y <= pos.y && pos.y < self.max.y
+    }
+
+    fn positions(self) -> impl Iterator<Item = Pos> {
+        (self.min.y..self.max.y)
+            .flat_map(move |y| (self.min.x..self.max.x).map(move |x| Pos::new(x, y)))
+    }
+}
+
+// What a bot does about a goal before it works on it.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub(crate) enum Precondition {
+    // The bot can work on the goal.
+    Met,
+    // The goal becomes possible in a later turn without the bot going anywhere, such as using a
+    // booster picked up in this turn.
+    Wait,
+    // There is nothing to do for the goal, so the bot goes on with the next one.
+    Skip,
+    // The goal has to be reached first.
+    Needs(Goal),
+    // The goal can not be reached any more, so the bot plans afresh.
+    Replan,
+}
+
+impl Goal {
+    pub(crate) fn precondition(self, system: &System, bot: &Bot) -> Precondition {
+        use BoosterKind::*;
+        use Precondition::*;
+        let pos = bot.pos_angle.pos;
+        match self {
+            Goal::GoTo(goal) if goal == pos => Skip,
+            Goal::GoTo(_) => Met,
+            // A booster is only picked up by moving onto it.
+            Goal::PickUp(goal) if system.booster_pos[goal].is_none() || goal == pos => Replan,
+            Goal::PickUp(_) => Met,
+            // Beacons are installed by PlaceBeacon, and mysterious points are not boosters.
+            Goal::Activate(Teleport) | Goal::Activate(Mysterious) => Replan,
+            Goal::Activate(kind) if system.inventory.held(kind) == 0 => Replan,
+            Goal::Activate(Cloning) if !system.mysterious_pos.contains(pos) => {
+                match system.find_mysterious(bot) {
+                    Ok(step) => Needs(Goal::GoTo(step.pos_angle.pos)),
+                    Err(_) => Replan,
+                }
+            }
+            Goal::Activate(kind) if system.inventory.count(kind, bot.time()) == 0 => Wait,
+            Goal::Activate(_) => Met,
+            Goal::PlaceBeacon(goal) if system.beacons.contains(&goal) => Skip,
+            Goal::PlaceBeacon(goal)
+                if system.mysterious_pos.contains(goal) || system.inventory.held(Teleport) == 0 =>
+            {
+                Replan
+            }
+            Goal::PlaceBeacon(goal) if goal != pos => Needs(Goal::GoTo(goal)),
+            Goal::PlaceBeacon(_) if system.inventory.count(Teleport, bot.time()) == 0 => Wait,
+            Goal::PlaceBeacon(_) => Met,
+            Goal::Sweep(region) if !region.positions().any(|pos| system.map.is_empty(pos)) => Skip,
+            Goal::Sweep(_) => Met,
+        }
+    }
+
+    // The action towards the goal, once its precondition is met. None if the bot can not get
+    // there.
+    pub(crate) fn action(self, system: &System, bot: &Bot) -> Option<Action> {
+        use BoosterKind::*;
+        match self {
+            Goal::GoTo(pos) | Goal::PickUp(pos) => system.move_to_action(bot, pos).ok(),
+            Goal::Activate(kind) => match kind {
+                ExtendManipulator => Some(Action::ExtendManipulator(
+                    bot.find_extend_manipulator_position(),
+                )),
+                FastWheels => Some(Action::AttachFastWheels),
+                Drill => Some(Action::AttachDrill),
+                Cloning => Some(Action::Cloning),
+                Teleport | Mysterious => None,
+            },
+            Goal::PlaceBeacon(_) => Some(Action::InstallBeacon),
+            Goal::Sweep(region) => system.sweep_action(bot, region).ok(),
+        }
+    }
+}
+
+/// The goals of a bot, done one after another. A bot without any paints.
+#[derive(Debug, Clone, PartialEq, Default, Hash)]
+pub struct Plan {
+    goals: Vec<Goal>,
+}
+
+impl Plan {
+    pub(crate) fn new(goals: Vec<Goal>) -> Plan {
+        Plan { goals }
+    }
+
+    pub fn goals(&self) -> &[Goal] {
+        &self.goals
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.goals.is_empty()
+    }
+
+    // Goes for the booster and uses it. Fast wheels are kept until a long straight run comes.
+    pub(crate) fn fetch(pos: Pos, kind: BoosterKind) -> Plan {
+        let mut goals = vec![Goal::PickUp(pos)];
+        if kind != BoosterKind::FastWheels {
+            goals.push(Goal::Activate(kind));
+        }
+        Plan { goals }
+    }
+
+    pub(crate) fn first(&self) -> Option<Goal> {
+        self.goals.first().cloned()
+    }
+
+    pub(crate) fn skip_first(&mut self) {
+        self.goals.remove(0);
+    }
+
+    // The plan with a goal put before the others, to meet a precondition of the first one.
+    pub(crate) fn prepend(&self, goal: Goal) -> Plan {
+        let mut goals = vec![goal];
+        goals.extend(&self.goals);
+        Plan { goals }
+    }
+
+    // The booster picked up for the plan, which the bot is yet to use.
+    pub(crate) fn holds(&self) -> Option<BoosterKind> {
+        match self
+            .goals
+            .iter()
+            .find(|goal| !matches!(goal, Goal::GoTo(_)))?
+        {
+            Goal::Activate(kind) => Some(*kind),
+            Goal::PlaceBeacon(_) => Some(BoosterKind::Teleport),
+            _ => None,
+        }
+    }
+
+    // Called once per action with the cells the bot moved through, which are two with fast
+    // wheels, and where it ended up. Boosters are picked up by passing over them, but cloning
+    // needs the bot to stay on the mysterious point. A sweep lasts until its precondition finds
+    // the region painted.
+    pub(crate) fn update(&mut self, passed: &[Pos], pos: Pos, action: Action) {
+        let mut used = action.booster();
+        while let Some(goal) = self.goals.first() {
+            let done = match *goal {
+                Goal::GoTo(goal) => pos == goal,
+                Goal::PickUp(goal) => passed.contains(&goal),
+                Goal::Activate(kind) => used.take() == Some(kind),
+                Goal::PlaceBeacon(goal) => {
+                    pos == goal && used.take() == Some(BoosterKind::Teleport)
+                }
+                Goal::Sweep(_) => false,
+            };
+            if !done {
+                break;
+            }
+            self.goals.remove(0);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn update_test() {
+        use Goal::*;
+        let booster = Pos::new(2, 0);
+        let spawn = Pos::new(4, 0);
+        let mut plan = Plan::new(vec![
+            PickUp(booster),
+            GoTo(spawn),
+            Activate(BoosterKind::Cloning),
+        ]);
+        assert_eq!(plan.holds(), None);
+
+        // Fast wheels pass over the booster.
+        plan.update(&[Pos::new(1, 0), booster], booster, Action::MoveRight);
+        assert_eq!(plan.first(), Some(GoTo(spawn)));
+        assert_eq!(plan.holds(), Some(BoosterKind::Cloning));
+        plan.update(&[Pos::new(3, 0)], Pos::new(3, 0), Action::MoveRight);
+        plan.update(&[spawn], spawn, Action::MoveRight);
+        assert_eq!(plan.goals(), &[Activate(BoosterKind::Cloning)]);
+
+        // An action uses one booster only.
+        let mut plan = Plan::new(vec![
+            Activate(BoosterKind::ExtendManipulator),
+            Activate(BoosterKind::ExtendManipulator),
+        ]);
+        let extend = Action::ExtendManipulator(PosDiff::new(1, 2));
+        plan.update(&[spawn], spawn, extend);
+        assert_eq!(plan.goals().len(), 1);
+        plan.update(&[spawn], spawn, Action::DoNothing);
+        assert_eq!(plan.goals().len(), 1);
+        plan.update(&[spawn], spawn, extend);
+        assert!(plan.is_empty());
+
+        // A beacon is placed by installing it on the cell, and a sweep is left to the
+        // precondition.
+        let region = Region::new(Pos::new(0, 0), Pos::new(2, 2));
+        let mut plan = Plan::new(vec![PlaceBeacon(spawn), Sweep(region)]);
+        assert_eq!(plan.holds(), Some(BoosterKind::Teleport));
+        plan.update(&[spawn], spawn, Action::DoNothing);
+        assert_eq!(plan.goals().len(), 2);
+        plan.update(&[spawn], spawn, Action::InstallBeacon);
+        assert_eq!(plan.goals(), &[Sweep(region)]);
+        plan.update(&[Pos::new(1, 1)], Pos::new(1, 1), Action::MoveLeft);
+        assert_eq!(plan.goals(), &[Sweep(region)]);
+        assert!(region.contains(Pos::new(1, 0)));
+        assert!(!region.contains(Pos::new(2, 0)));
+    }
+}
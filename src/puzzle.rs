@@ -1,7 +1,5 @@
-// use std::collections::HashSet;
-// use std::io::prelude::*;
-
-// use crate::prelude::*;
+use crate::grid::*;
+use crate::prelude::*;
 use crate::task::*;
 
 // puzzle ::= bNum, eNum, tSize, vMin, vMax, mNum, fNum, dNum,rNum,cNum, xNum # iSqs # oSqs
@@ -9,30 +7,59 @@ use crate::task::*;
 
 // 1,1,150,400,1200,6,10,5,1,3,4#(73,61),(49,125),(73,110),(98,49),(126,89),(68,102),(51,132),(101,123),(22,132),(71,120),(97,129),(118,76),(85,100),(88,22),(84,144),(93,110),(96,93),(113,138),(91,52),(27,128),(84,140),(93,143),(83,17),(123,85),(50,74),(139,97),(101,110),(77,56),(86,23),(117,59),(133,126),(83,135),(76,90),(70,12),(12,141),(116,87),(102,76),(19,138),(86,129),(86,128),(83,60),(100,98),(60,105),(61,103),(94,99),(130,124),(141,132),(68,84),(86,143),(72,119)#(145,82),(20,65),(138,99),(38,137),(85,8),(125,104),(117,48),(57,48),(64,119),(3,25),(40,22),(82,54),(121,119),(1,34),(43,98),(97,120),(10,90),(15,32),(41,13),(86,40),(3,83),(2,127),(4,40),(139,18),(96,49),(53,22),(5,103),(112,33),(38,47),(16,121),(133,99),(113,45),(50,5),(94,144),(16,0),(93,113),(18,141),(36,25),(56,120),(3,126),(143,144),(99,62),(144,117),(48,97),(69,9),(0,9),(141,16),(55,68),(81,3),(47,53)
 
+/// A block puzzle of the lambda chain: the constraints a generated task has to meet.
 #[derive(Default)]
 pub struct Puzzle {
     pub b_num: u64,
+    // The epoch, which the constraints of the task do not depend on.
     pub e_num: u64,
     pub t_size: u64,
     pub v_min: u64,
     pub v_max: u64,
     pub m_num: u64,
     pub f_num: u64,
+    pub d_num: u64,
     pub r_num: u64,
     pub c_num: u64,
     pub x_num: u64,
     pub i_sqs: Vec<Pos>,
     pub o_sqs: Vec<Pos>,
-    // My fileds
+    // My fileds, for dump_map.
     pub max_x: i32,
     pub max_y: i32,
 }
 
 impl Puzzle {
-    pub fn parse(s: &str) -> Puzzle {
-        let sss = s.split('#').collect::<Vec<_>>();
-        let i_sqs = Task::parse_tour(sss[1]);
-        let o_sqs = Task::parse_tour(sss[2]);
+    /// Parses a .cond file.
+    pub fn parse(s: &str) -> Result<Puzzle> {
+        let sss = s.trim().split('#').collect::<Vec<_>>();
+        if sss.len() != 3 {
+            return Err(failure::format_err!(
+                "a puzzle has 3 parts separated by '#', not {}",
+                sss.len()
+            ));
+        }
+        let nums = sss[0]
+            .split(',')
+            .map(|n| n.parse())
+            .collect::<std::result::Result<Vec<u64>, _>>()?;
+        if nums.len() != 11 {
+            return Err(failure::format_err!(
+                "a puzzle has 11 numbers, not {}",
+                nums.len()
+            ));
+        }
+        let t_size = nums[2];
+        let i_sqs = Task::parse_tour(sss[1]).context("iSqs")?;
+        let o_sqs = Task::parse_tour(sss[2]).context("oSqs")?;
+        if t_size > i32::MAX as u64 {
+            return Err(failure::format_err!("too large a puzzle: {}", t_size));
+        }
+        let off =
+            |pos: &Pos| pos.x < 0 || pos.y < 0 || pos.x as u64 >= t_size || pos.y as u64 >= t_size;
+        if let Some(pos) = i_sqs.iter().chain(&o_sqs).find(|pos| off(pos)) {
+            return Err(failure::format_err!("off the puzzle: {:?}", pos));
+        }
 
         let all = {
             let mut all = i_sqs.clone();
@@ -40,15 +67,113 @@ impl Puzzle {
             all
         };
 
-        let max_x = all.iter().map(|p| p.x).max().unwrap() + 1;
-        let max_y = all.iter().map(|p| p.y).max().unwrap() + 1;
-        Puzzle {
+        let max_x = all.iter().map(|p| p.x).max().unwrap_or(0) + 1;
+        let max_y = all.iter().map(|p| p.y).max().unwrap_or(0) + 1;
+        Ok(Puzzle {
+            b_num: nums[0],
+            e_num: nums[1],
+            t_size: nums[2],
+            v_min: nums[3],
+            v_max: nums[4],
+            m_num: nums[5],
+            f_num: nums[6],
+            d_num: nums[7],
+            r_num: nums[8],
+            c_num: nums[9],
+            x_num: nums[10],
             i_sqs,
             o_sqs,
             max_x,
             max_y,
-            ..Default::default()
+        })
+    }
+
+    /// Generates a task which satisfies the puzzle. The map starts as the whole tSize x tSize
+    /// square, and a thin channel is carved from the outside to each of oSqs. Notches are added
+    /// along the border until the map has enough vertices.
+    pub fn solve(&self) -> Result<Task> {
+        let size = self.t_size as i32;
+        let mut shape = Shape::new(size, &self.i_sqs);
+
+        let mut o_sqs = self.o_sqs.clone();
+        o_sqs.sort_by_key(|pos| pos.x.min(pos.y).min(size - 1 - pos.x).min(size - 1 - pos.y));
+        for pos in o_sqs {
+            shape.carve_to(pos)?;
         }
+
+        let mut vertices = shape.tour().len();
+        let candidates = shape
+            .inside
+            .positions()
+            .filter(|pos| shape.is_border(*pos))
+            .collect::<Vec<_>>();
+        for pos in candidates {
+            if vertices as u64 >= self.v_min {
+                break;
+            }
+            if !shape.can_remove(pos, &[]) {
+                continue;
+            }
+            shape.inside[pos] = false;
+            let notched = shape.tour().len();
+            if notched > vertices && notched as u64 <= self.v_max {
+                vertices = notched;
+            } else {
+                shape.inside[pos] = true;
+            }
+        }
+        if (vertices as u64) < self.v_min || vertices as u64 > self.v_max {
+            return Err(failure::format_err!(
+                "puzzle: {} vertices, which should be in [{}, {}]",
+                vertices,
+                self.v_min,
+                self.v_max
+            ));
+        }
+
+        let cells = shape
+            .inside
+            .iter()
+            .filter(|(_, inside)| **inside)
+            .map(|(pos, _)| pos)
+            .collect::<Vec<_>>();
+        if (cells.len() as u64) * 5 < self.t_size * self.t_size {
+            return Err(failure::err_msg("puzzle: the map is too small"));
+        }
+
+        use BoosterKind::*;
+        let kinds = [
+            (ExtendManipulator, self.m_num),
+            (FastWheels, self.f_num),
+            (Drill, self.d_num),
+            (Teleport, self.r_num),
+            (Cloning, self.c_num),
+            (Mysterious, self.x_num),
+        ]
+        .iter()
+        .flat_map(|(kind, num)| std::iter::repeat_n(*kind, *num as usize))
+        .collect::<Vec<_>>();
+        // Spread the bot and boosters over the map, one cell each.
+        let count = kinds.len() + 1;
+        if cells.len() < count {
+            return Err(failure::err_msg("puzzle: too many boosters"));
+        }
+        let spread = |i: usize| cells[i * cells.len() / count];
+
+        Task::new(
+            self.b_num,
+            shape.tour(),
+            spread(0),
+            vec![],
+            kinds
+                .into_iter()
+                .enumerate()
+                .map(|(i, kind)| Booster {
+                    pos: spread(i + 1),
+                    kind,
+                })
+                .collect(),
+        )
     }
 
     #[cfg(test)]
@@ -73,6 +198,238 @@ impl Puzzle {
     }
 }
 
+// Neighbours of a cell in circular order, starting from the east.
+const RING: [(i32, i32); 8] = [
+    (1, 0),
+    (1, 1),
+    (0, 1),
+    (-1, 1),
+    (-1, 0),
+    (-1, -1),
+    (0, -1),
+    (1, -1),
+];
+
+// A set of cells which is kept a simple polygon: connected, without holes, and without two
+// cells touching only at a corner.
+pub(crate) struct Shape {
+    pub(crate) inside: Grid<bool>,
+    // Cells which must stay inside.
+    keep: BitGrid,
+}
+
+impl Shape {
+    pub(crate) fn new(size: i32, keep_cells: &[Pos]) -> Shape {
+        let mut keep = BitGrid::new(size, size);
+        for pos in keep_cells {
+            keep.insert(*pos);
+        }
+        Shape {
+            inside: Grid::new(size, size, true),
+            keep,
+        }
+    }
+
+    pub(crate) fn contains(&self, pos: Pos) -> bool {
+        self.inside.get(pos) == Some(&true)
+    }
+
+    pub(crate) fn is_border(&self, pos: Pos) -> bool {
+        self.contains(pos)
+            && PosDiff::gen_all_diff()
+                .iter()
+                .any(|diff| !self.contains(pos + *diff))
+    }
+
+    // Whether the cell can be taken out of the shape, as if the cells in `removed` were already
+    // out, keeping the shape a simple polygon. Only the 3x3 neighbourhood matters: the cell must
+    // be a simple point (one inside component 4-adjacent to it, one outside component), and
+    // removing it must not leave two inside cells touching only at a corner.
+    pub(crate) fn can_remove(&self, pos: Pos, removed: &[Pos]) -> bool {
+        if !self.contains(pos) || self.keep.contains(pos) {
+            return false;
+        }
+        let inside = |p: Pos| p != pos && self.contains(p) && !removed.contains(&p);
+        let ring = RING
+            .iter()
+            .map(|(dx, dy)| inside(Pos::new(pos.x + dx, pos.y + dy)))
+            .collect::<Vec<_>>();
+
+        // Components of the ring cells with the given state. Inside cells are 4-connected and
+        // outside cells are 8-connected.
+        let components = |state: bool, eight_connected: bool| {
+            let mut label = [usize::MAX; 8];
+            let mut count = 0;
+            for i in 0..8 {
+                if ring[i] != state || label[i] != usize::MAX {
+                    continue;
+                }
+                label[i] = count;
+                let mut stack = vec![i];
+                while let Some(j) = stack.pop() {
+                    for k in 0..8 {
+                        let (dx, dy) = (RING[j].0 - RING[k].0, RING[j].1 - RING[k].1);
+                        let dist = if eight_connected {
+                            dx.abs().max(dy.abs())
+                        } else {
+                            dx.abs() + dy.abs()
+                        };
+                        if ring[k] == state && label[k] == usize::MAX && dist == 1 {
+                            label[k] = count;
+                            stack.push(k);
+                        }
+                    }
+                }
+                count += 1;
+            }
+            (label, count)
+        };
+
+        let (inside_label, _) = components(true, false);
+        let adjacent_inside = [0, 2, 4, 6]
+            .iter()
+            .filter(|i| ring[**i])
+            .map(|i| inside_label[*i])
+            .collect::<HashSet<_>>();
+        let (_, outside_count) = components(false, true);
+        if adjacent_inside.len() != 1 || outside_count != 1 {
+            return false;
+        }
+
+        // With the cell out, no 2x2 block around it may be a checkerboard.
+        [1, 3, 5, 7]
+            .iter()
+            .all(|corner| ring[*corner] || !ring[corner - 1] || !ring[(corner + 1) % 8])
+    }
+
+    // Removes a path of cells from the outside to the target.
+    fn carve_to(&mut self, target: Pos) -> Result<()> {
+        let mut blocked = HashSet::new();
+        for _ in 0..20 {
+            let path = match self.find_channel(target, &blocked) {
+                Some(path) => path,
+                None => break,
+            };
+            let mut removed = vec![];
+            for pos in &path {
+                if !self.can_remove(*pos, &[]) {
+                    blocked.insert(*pos);
+                    break;
+                }
+                self.inside[*pos] = false;
+                removed.push(*pos);
+            }
+            if removed.len() == path.len() {
+                return Ok(());
+            }
+            for pos in removed.into_iter().rev() {
+                self.inside[pos] = true;
+            }
+        }
+        Err(failure::format_err!(
+            "puzzle: can not carve a channel to {:?}",
+            target
+        ))
+    }
+
+    // BFS from the border cells to the target. The path is ordered from the border, which is the
+    // order to remove the cells in.
+    fn find_channel(&self, target: Pos, blocked: &HashSet<Pos>) -> Option<Vec<Pos>> {
+        if !self.contains(target) {
+            return Some(vec![]);
+        }
+        let mut parent: Grid<Option<Pos>> =
+            Grid::new(self.inside.width(), self.inside.height(), None);
+        let mut visited = BitGrid::new(self.inside.width(), self.inside.height());
+        let mut q = VecDeque::new();
+        for pos in self.inside.positions() {
+            if self.is_border(pos) && !blocked.contains(&pos) && self.can_remove(pos, &[]) {
+                visited.insert(pos);
+                q.push_back(pos);
+            }
+        }
+
+        // Cells removed just before `pos` on its path, which are the ones close enough to
+        // matter for can_remove.
+        let chain = |pos: Pos, parent: &Grid<Option<Pos>>| {
+            let mut chain = vec![pos];
+            while chain.len() < 3 {
+                match parent[*chain.last().unwrap()] {
+                    Some(prev) => chain.push(prev),
+                    None => break,
+                }
+            }
+            chain
+        };
+
+        while let Some(pos) = q.pop_front() {
+            if pos == target {
+                let mut path = vec![pos];
+                while let Some(prev) = parent[*path.last().unwrap()] {
+                    path.push(prev);
+                }
+                path.reverse();
+                return Some(path);
+            }
+            let removed = chain(pos, &parent);
+            for next in self.inside.neighbours(pos).collect::<Vec<_>>() {
+                if visited.contains(next) || blocked.contains(&next) {
+                    continue;
+                }
+                if self.can_remove(next, &removed) {
+                    visited.insert(next);
+                    parent[next] = Some(pos);
+                    q.push_back(next);
+                }
+            }
+        }
+        None
+    }
+
+    // The outline of the shape, counter-clockwise, one position per vertex.
+    pub(crate) fn tour(&self) -> Vec<Pos> {
+        // Edges with the inside on their left, keyed by their start.
+        let mut next = HashMap::new();
+        for (pos, inside) in self.inside.iter() {
+            if !*inside {
+                continue;
+            }
+            let (x, y) = (pos.x, pos.y);
+            if !self.contains(Pos::new(x, y - 1)) {
+                next.insert(Pos::new(x, y), Pos::new(x + 1, y));
+            }
+            if !self.contains(Pos::new(x + 1, y)) {
+                next.insert(Pos::new(x + 1, y), Pos::new(x + 1, y + 1));
+            }
+            if !self.contains(Pos::new(x, y + 1)) {
+                next.insert(Pos::new(x + 1, y + 1), Pos::new(x, y + 1));
+            }
+            if !self.contains(Pos::new(x - 1, y)) {
+                next.insert(Pos::new(x, y + 1), Pos::new(x, y));
+            }
+        }
+
+        let start = *next.keys().min_by_key(|pos| (pos.y, pos.x)).unwrap();
+        let mut points = vec![start];
+        let mut current = next[&start];
+        while current != start {
+            points.push(current);
+            current = next[&current];
+        }
+
+        // Keep only the corners.
+        let n = points.len();
+        (0..n)
+            .filter(|i| {
+                let prev = points[(i + n - 1) % n];
+                let next = points[(i + 1) % n];
+                prev.x != next.x && prev.y != next.y
+            })
+            .map(|i| points[i])
+            .collect()
+    }
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
@@ -81,8 +438,36 @@ mod test {
     fn puzzle_dump_test() {
         let puzzle = "1,1,150,400,1200,6,10,5,1,3,4#(73,61),(49,125),(73,110),(98,49),(126,89),(68,102),(51,132),(101,123),(22,132),(71,120),(97,129),(118,76),(85,100),(88,22),(84,144),(93,110),(96,93),(113,138),(91,52),(27,128),(84,140),(93,143),(83,17),(123,85),(50,74),(139,97),(101,110),(77,56),(86,23),(117,59),(133,126),(83,135),(76,90),(70,12),(12,141),(116,87),(102,76),(19,138),(86,129),(86,128),(83,60),(100,98),(60,105),(61,103),(94,99),(130,124),(141,132),(68,84),(86,143),(72,119)#(145,82),(20,65),(138,99),(38,137),(85,8),(125,104),(117,48),(57,48),(64,119),(3,25),(40,22),(82,54),(121,119),(1,34),(43,98),(97,120),(10,90),(15,32),(41,13),(86,40),(3,83),(2,127),(4,40),(139,18),(96,49),(53,22),(5,103),(112,33),(38,47),(16,121),(133,99),(113,45),(50,5),(94,144),(16,0),(93,113),(18,141),(36,25),(56,120),(3,126),(143,144),(99,62),(144,117),(48,97),(69,9),(0,9),(141,16),(55,68),(81,3),(47,53)";
 
-        let _a = Puzzle::parse(puzzle).dump_map();
+        let _a = Puzzle::parse(puzzle).unwrap().dump_map();
         // println!("{}", a);
         // assert_eq!(a, "".to_string());
     }
+
+    proptest::proptest! {
+        #[test]
+        fn parse_fuzz_test(s in "[0-9,#()]{0,60}|\\PC*") {
+            let _ = Puzzle::parse(&s);
+        }
+    }
+
+    #[test]
+    fn puzzle_solve_test() -> Result<()> {
+        let puzzle = Puzzle::parse("1,1,150,400,1200,6,10,5,1,3,4#(73,61),(49,125),(73,110),(98,49),(126,89),(68,102),(51,132),(101,123),(22,132),(71,120),(97,129),(118,76),(85,100),(88,22),(84,144),(93,110),(96,93),(113,138),(91,52),(27,128),(84,140),(93,143),(83,17),(123,85),(50,74),(139,97),(101,110),(77,56),(86,23),(117,59),(133,126),(83,135),(76,90),(70,12),(12,141),(116,87),(102,76),(19,138),(86,129),(86,128),(83,60),(100,98),(60,105),(61,103),(94,99),(130,124),(141,132),(68,84),(86,143),(72,119)#(145,82),(20,65),(138,99),(38,137),(85,8),(125,104),(117,48),(57,48),(64,119),(3,25),(40,22),(82,54),(121,119),(1,34),(43,98),(97,120),(10,90),(15,32),(41,13),(86,40),(3,83),(2,127),(4,40),(139,18),(96,49),(53,22),(5,103),(112,33),(38,47),(16,121),(133,99),(113,45),(50,5),(94,144),(16,0),(93,113),(18,141),(36,25),(56,120),(3,126),(143,144),(99,62),(144,117),(48,97),(69,9),(0,9),(141,16),(55,68),(81,3),(47,53)")?;
+        let task = puzzle.solve()?;
+        let vertices = task.map().len() as u64;
+        assert!(puzzle.v_min <= vertices && vertices <= puzzle.v_max);
+        assert_eq!(task.boosters().len(), 6 + 10 + 5 + 1 + 3 + 4);
+        assert_eq!(
+            task.map().iter().collect::<HashSet<_>>().len(),
+            task.map().len()
+        );
+
+        // The task should read back as the same map.
+        let task = Task::parse(1, &task.to_string())?;
+        let map = Map::new(task);
+        assert!(puzzle.i_sqs.iter().all(|pos| map.is_free(*pos)));
+        assert!(puzzle.o_sqs.iter().all(|pos| !map.is_free(*pos)));
+        assert!(map.empty_cell_count as u64 * 5 >= puzzle.t_size * puzzle.t_size);
+        Ok(())
+    }
 }
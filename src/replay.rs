@@ -0,0 +1,229 @@
+use std::collections::BTreeMap;
+
+use crate::prelude::*;
+use crate::system::*;
+use crate::task::*;
+
+/// The result of replaying a valid solution.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Replay {
+    pub turns: usize,
+    pub bots: usize,
+    // How many boosters of each kind were used.
+    pub used: BTreeMap<BoosterKind, usize>,
+}
+
+/// Parses a .sol file into the actions of each bot.
+pub fn parse_solution(s: &str) -> Result<Vec<Vec<Action>>> {
+    s.trim().split('#').map(parse_actions).collect()
+}
+
+fn parse_actions(s: &str) -> Result<Vec<Action>> {
+    use Action::*;
+    let mut actions = vec![];
+    let mut chars = s.chars();
+    while let Some(c) = chars.next() {
+        actions.push(match c {
+            'W' => MoveUp,
+            'S' => MoveDown,
+            'A' => MoveLeft,
+            'D' => MoveRight,
+            'Z' => DoNothing,
+            'E' => TurnClockWise,
+            'Q' => TurnCounterClockWise,
+            'F' => AttachFastWheels,
+            'L' => AttachDrill,
+            'C' => Cloning,
+            'R' => InstallBeacon,
+            'B' => {
+                let (dx, dy) = parse_args(c, &mut chars)?;
+                ExtendManipulator(PosDiff::new(dx, dy))
+            }
+            'T' => {
+                let (x, y) = parse_args(c, &mut chars)?;
+                Shift(Pos::new(x, y))
+            }
+            c => return Err(failure::format_err!("unsupported action: {}", c)),
+        });
+    }
+    Ok(actions)
+}
+
+// Parses the "(x,y)" after the action c.
+fn parse_args(c: char, chars: &mut std::str::Chars) -> Result<(i32, i32)> {
+    let rest = chars.as_str();
+    let end = rest
+        .find(')')
+        .ok_or_else(|| failure::format_err!("unterminated {}(", c))?;
+    let args = rest[..end]
+        .strip_prefix('(')
+        .ok_or_else(|| failure::format_err!("{} without arguments", c))?
+        .split(',')
+        .map(|n| n.parse::<i32>())
+        .collect::<std::result::Result<Vec<_>, _>>()?;
+    if args.len() != 2 {
+        return Err(failure::format_err!("{} takes two arguments: {}", c, rest));
+    }
+    *chars = rest[end + 1..].chars();
+    Ok((args[0], args[1]))
+}
+
+/// Parses a .buy file, the boosters bought before the start.
+pub fn parse_buy(s: &str) -> Result<Vec<BoosterKind>> {
+    use BoosterKind::*;
+    s.trim()
+        .chars()
+        .map(|c| match c {
+            'B' => Ok(ExtendManipulator),
+            'F' => Ok(FastWheels),
+            'L' => Ok(Drill),
+            'R' => Ok(Teleport),
+            'C' => Ok(Cloning),
+            c => Err(failure::format_err!("unknown booster: {}", c)),
+        })
+        .collect()
+}
+
+/// Replays a solution following the contest rules, and fails at the first action which is not
+/// allowed, or if some cells are left unpainted. The simulator keeps the inventory of boosters.
+pub fn replay(map: Map, solution: &str, buy: &[BoosterKind]) -> Result<Replay> {
+    let programs = parse_solution(solution)?;
+    let mut system = System::from_map(map);
+    for kind in buy {
+        system.inventory.add(*kind, 0);
+    }
+
+    let mut used = BTreeMap::new();
+    let mut next = vec![0; programs.len()];
+    let mut turn = 0;
+    while !system.is_solved() {
+        let bots = system.bots.len();
+        if (0..bots).all(|i| {
+            programs
+                .get(i)
+                .is_none_or(|actions| next[i] == actions.len())
+        }) {
+            return Err(failure::format_err!(
+                "{} cells are left unpainted after {} turns",
+                system.map.empty_cell_count,
+                turn
+            ));
+        }
+        turn += 1;
+        for (i, (actions, next)) in programs.iter().zip(&mut next).enumerate().take(bots) {
+            let action = match actions.get(*next) {
+                Some(action) => *action,
+                None => continue,
+            };
+            *next += 1;
+            let action = check_action(&system, i, action)
+                .with_context(|_| format!("turn {}, bot {}: {}", turn, i, action))?;
+            if let Some(kind) = action.booster() {
+                *used.entry(kind).or_default() += 1;
+            }
+            system.step(i, action);
+        }
+    }
+    if programs.len() > system.bots.len() {
+        return Err(failure::format_err!(
+            "{} action lists for {} bots",
+            programs.len(),
+            system.bots.len()
+        ));
+    }
+    Ok(Replay {
+        turns: turn,
+        bots: system.bots.len(),
+        used,
+    })
+}
+
+// Checks the action, and returns it as the simulator takes it, with a manipulator relative to the
+// bot's angle.
+fn check_action(system: &System, i: usize, action: Action) -> Result<Action> {
+    use Action::*;
+    let action = match action {
+        ExtendManipulator(posdiff) => {
+            ExtendManipulator(posdiff.turn(system.bots[i].pos_angle.angle.inverse()))
+        }
+        action => action,
+    };
+    system.check_action(i, action)?;
+    Ok(action)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn replay_test() -> Result<()> {
+        let system = System::new(2)?.plan_cloning()?.solved;
+        let solution = system.solution();
+        let map = Map::new(Task::read_with_id(2)?);
+        let replay = replay(map.clone(), &solution.solution, &[])?;
+        assert_eq!(replay.turns, solution.score);
+        assert_eq!(replay.bots, system.bots.len());
+        assert_eq!(
+            replay.used.get(&BoosterKind::Cloning).cloned().unwrap_or(0),
+            system.bots.len() - 1
+        );
+
+        // Cloning needs a booster.
+        assert!(super::replay(map.clone(), "C", &[]).is_err());
+        // Not all cells are painted.
+        assert!(super::replay(map.clone(), "Z", &[]).is_err());
+        assert!(super::replay(map, "B(1,0", &[]).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn replay_beacon_test() -> Result<()> {
+        let map = Map::new(Task::parse(0, "(0,0),(6,0),(6,1),(0,1)#(0,0)##")?);
+        let buy = [BoosterKind::Teleport];
+        let replay = replay(map.clone(), "RDDDT(0,0)DDDD", &buy)?;
+        assert_eq!(replay.turns, 9);
+        assert_eq!(replay.used.get(&BoosterKind::Teleport), Some(&1));
+
+        // A beacon needs a booster, and a bot only teleports to a beacon.
+        assert!(super::replay(map.clone(), "RDDDDD", &[]).is_err());
+        assert!(super::replay(map.clone(), "RRDDDD", &buy).is_err());
+        assert!(super::replay(map, "RDDDT(1,0)DDDD", &buy).is_err());
+        Ok(())
+    }
+
+    proptest::proptest! {
+        #[test]
+        fn parse_fuzz_test(s in "[WSADZEQFLCBRT(),#0-9-]{0,40}|\\PC*") {
+            let _ = parse_solution(&s);
+            let _ = parse_buy(&s);
+        }
+    }
+
+    #[test]
+    fn parse_solution_test() -> Result<()> {
+        use Action::*;
+        assert_eq!(
+            parse_solution("WB(1,-2)F#QZ")?,
+            vec![
+                vec![
+                    MoveUp,
+                    ExtendManipulator(PosDiff::new(1, -2)),
+                    AttachFastWheels
+                ],
+                vec![TurnCounterClockWise, DoNothing],
+            ]
+        );
+        assert_eq!(
+            parse_solution("RT(1,2)")?,
+            vec![vec![InstallBeacon, Shift(Pos::new(1, 2))]]
+        );
+        assert!(parse_solution("T(1)").is_err());
+        assert!(parse_solution("T1,2").is_err());
+        assert_eq!(
+            parse_buy("BC\n")?,
+            vec![BoosterKind::ExtendManipulator, BoosterKind::Cloning]
+        );
+        Ok(())
+    }
+}
@@ -1,5 +1,16 @@
+use std::time::Instant;
+
+use failure::Fail;
+use rayon::prelude::*;
+
+use crate::booster::*;
+use crate::cloning::*;
+use crate::grid::*;
+use crate::plan::*;
 use crate::prelude::*;
 use crate::task::*;
+use crate::trace::*;
+use crate::visibility::*;
 
 #[derive(Debug, Copy, Clone, PartialEq)]
 pub enum Action {
@@ -14,14 +25,27 @@ pub enum Action {
     AttachFastWheels,
     Cloning,
     AttachDrill,
+    InstallBeacon,
+    // Teleports the bot to the beacon installed on the cell.
+    Shift(Pos),
 }
 
 impl Action {
     fn is_move(&self) -> bool {
+        use Action::*;
+        matches!(self, MoveUp | MoveDown | MoveLeft | MoveRight)
+    }
+
+    /// The booster the action uses up.
+    pub fn booster(&self) -> Option<BoosterKind> {
         use Action::*;
         match self {
-            MoveUp | MoveDown | MoveLeft | MoveRight => true,
-            _ => false,
+            ExtendManipulator(_) => Some(BoosterKind::ExtendManipulator),
+            AttachFastWheels => Some(BoosterKind::FastWheels),
+            AttachDrill => Some(BoosterKind::Drill),
+            Cloning => Some(BoosterKind::Cloning),
+            InstallBeacon => Some(BoosterKind::Teleport),
+            _ => None,
         }
     }
 }
@@ -44,19 +68,21 @@ impl std::fmt::Display for Action {
                 ExtendManipulator(posdiff) => format!("B({},{})", posdiff.dx, posdiff.dy),
                 Cloning => "C".to_string(),
                 AttachDrill => "L".to_string(),
+                InstallBeacon => "R".to_string(),
+                Shift(pos) => format!("T({},{})", pos.x, pos.y),
             }
         )
     }
 }
 
-#[derive(Debug, Clone)]
-struct Manipulator {
-    posdiff: PosDiff,
-    reachable_cell: Vec<PosDiff>,
+#[derive(Debug, Clone, PartialEq)]
+pub struct Manipulator {
+    pub(crate) posdiff: PosDiff,
+    pub(crate) reachable_cell: Vec<PosDiff>,
 }
 
 impl Manipulator {
-    fn plot(posdiff: PosDiff) -> Vec<PosDiff> {
+    pub(crate) fn plot(posdiff: PosDiff) -> Vec<PosDiff> {
         let pos_dx = posdiff.dx.abs();
         let pos_dy = posdiff.dy.abs();
 
@@ -102,13 +128,18 @@ impl Manipulator {
         plot
     }
 
-    fn new(posdiff: PosDiff) -> Manipulator {
+    pub(crate) fn new(posdiff: PosDiff) -> Manipulator {
         Manipulator {
             posdiff,
             reachable_cell: Manipulator::plot(posdiff),
         }
     }
 
+    /// The cell the manipulator reaches, relative to the bot facing east.
+    pub fn posdiff(&self) -> PosDiff {
+        self.posdiff
+    }
+
     pub fn can_mark(&self, pos_angle: PosAngle, map: &Map) -> bool {
         map.is_empty(pos_angle + self.posdiff)
             && self.reachable_cell.iter().all(|reach| {
@@ -120,28 +151,16 @@ impl Manipulator {
 
 type Manipulators = Vec<Manipulator>;
 
-#[derive(Debug, Copy, Clone, Hash)]
-enum Order {
-    MoveToExtendManipulator(Pos),
-    DoExtendManipulator,
-    MoveToClone(Pos),
-    FindMysterious,
-    MoveToMysterious(Pos),
-    DoClone,
-    MoveToFastWheel(Pos),
-    DoFastWheels,
-    MoveToDrill(Pos),
-    DoDrill,
-}
-
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct Bot {
-    pos_angle: PosAngle,
-    manipulators: Manipulators,
-    fast_wheel_timer: usize,
-    drill_timer: usize,
-    order: Option<Order>,
-    record: Vec<Action>,
+    pub(crate) pos_angle: PosAngle,
+    pub(crate) manipulators: Manipulators,
+    pub(crate) fast_wheel_timer: usize,
+    pub(crate) drill_timer: usize,
+    plan: Plan,
+    // The turn at which this bot was spawned. Its first action is taken in the next turn.
+    pub(crate) spawn_turn: usize,
+    pub(crate) record: Vec<Action>,
 }
 
 impl Bot {
@@ -156,12 +175,46 @@ impl Bot {
             ],
             fast_wheel_timer: 0,
             drill_timer: 0,
-            order: None,
+            plan: Plan::default(),
+            spawn_turn: 0,
             record: vec![],
         }
     }
 
-    fn mark_map(&self, map: &mut Map) {
+    /// The turn this bot has reached.
+    pub fn time(&self) -> usize {
+        self.spawn_turn + self.record.len()
+    }
+
+    pub fn pos_angle(&self) -> PosAngle {
+        self.pos_angle
+    }
+
+    pub fn manipulators(&self) -> &[Manipulator] {
+        &self.manipulators
+    }
+
+    /// Turns left with fast wheels attached.
+    pub fn fast_wheel_timer(&self) -> usize {
+        self.fast_wheel_timer
+    }
+
+    /// Turns left with a drill attached.
+    pub fn drill_timer(&self) -> usize {
+        self.drill_timer
+    }
+
+    pub fn plan(&self) -> &Plan {
+        &self.plan
+    }
+
+    /// The actions taken so far, as written in a .sol file.
+    pub fn record(&self) -> &[Action] {
+        &self.record
+    }
+
+    // Returns cells which are newly painted.
+    pub(crate) fn mark_map(&self, map: &mut Map) -> Vec<Pos> {
         let mut marked = vec![];
 
         for manipulator in &self.manipulators {
@@ -170,40 +223,10 @@ impl Bot {
                 marked.push(self.pos_angle + manipulator.posdiff);
             }
         }
-        for mark in marked {
-            map.mark_pos(mark);
-        }
-    }
-
-    fn number_of_possible_mark_with_this_pos(
-        pos_angle: PosAngle,
-        manipulators: &[Manipulator],
-        map: &Map,
-    ) -> usize {
-        manipulators
-            .iter()
-            .filter(|manipulator| manipulator.can_mark(pos_angle, map))
-            .count()
-    }
-
-    fn number_of_ajd_empty_cells_of_manipulators_with_this_pos(
-        pos_angle: PosAngle,
-        manipulators: &[Manipulator],
-        map: &Map,
-    ) -> usize {
-        let mut manipulator_cells = HashSet::new();
-        let mut empty_cells = HashSet::new();
-        for manipulator in manipulators {
-            let pos = pos_angle + manipulator.posdiff;
-            manipulator_cells.insert(pos);
-            for diff in PosDiff::gen_all_diff() {
-                let adj = pos + *diff;
-                if map.is_empty(adj) {
-                    empty_cells.insert(adj);
-                }
-            }
-        }
-        empty_cells.difference(&manipulator_cells).count()
+        marked
+            .into_iter()
+            .filter(|mark| map.mark_pos(*mark))
+            .collect()
     }
 
     #[allow(dead_code)]
@@ -229,9 +252,18 @@ impl Bot {
             .count()
     }
 
-    fn find_extend_manipulator_position(&self) -> PosDiff {
+    // How far from the bot its manipulators paint.
+    fn reach(&self) -> i32 {
+        self.manipulators
+            .iter()
+            .map(|manipulator| manipulator.posdiff.dx.abs() + manipulator.posdiff.dy.abs())
+            .max()
+            .unwrap_or(0)
+    }
+
+    pub(crate) fn find_extend_manipulator_position(&self) -> PosDiff {
         // MVP
-        if self.manipulators.len() % 2 == 0 {
+        if self.manipulators.len().is_multiple_of(2) {
             // Attach north
             PosDiff::new(1, (self.manipulators.len() / 2) as i32)
         } else {
@@ -240,30 +272,39 @@ impl Bot {
         }
     }
 
-    fn apply_action(&mut self, action: Action, map: &mut Map, fast_wheel_second_move: bool) {
+    // Returns a drilled cell, if any.
+    fn apply_action(
+        &mut self,
+        action: Action,
+        map: &mut Map,
+        fast_wheel_second_move: bool,
+    ) -> Option<Pos> {
         use Action::*;
+        let mut drilled = None;
         match action {
             MoveUp | MoveDown | MoveLeft | MoveRight => {
                 self.pos_angle = {
                     let next = self.pos_angle.apply_action(action);
                     if fast_wheel_second_move {
-                        if self.drill_timer > 0 {
-                            if map.is_in_range(next.pos) && map.is_wall(next.pos) {
+                        if !map.is_in_range(next.pos) {
+                            // Drill, but don't move out of boundaries.
+                            self.pos_angle
+                        } else if map.is_wall(next.pos) {
+                            if self.drill_timer > 0 {
                                 map.do_drill(next.pos);
+                                drilled = Some(next.pos);
                                 next
                             } else {
-                                // Drill, but don't move out of boundaries.
+                                // collide in fast wheel 2nd move is okay. Don't move.
                                 self.pos_angle
                             }
-                        } else if !map.is_free(next.pos) {
-                            // collide in fast wheel 2nd move is okay. Don't move.
-                            self.pos_angle
                         } else {
                             next
                         }
                     } else {
                         if self.drill_timer > 0 && map.is_wall(next.pos) {
                             map.do_drill(next.pos);
+                            drilled = Some(next.pos);
                         }
                         assert!(map.is_free(next.pos));
                         next
@@ -281,9 +322,12 @@ impl Bot {
                 self.manipulators.push(Manipulator::new(posdiff));
                 debug!("extend manipulator: bot: {:?}", self);
             }
-            Cloning => {
+            Cloning | InstallBeacon => {
                 // No effect on this bot.
             }
+            Shift(pos) => {
+                self.pos_angle.pos = pos;
+            }
             AttachFastWheels => {
                 self.fast_wheel_timer += 50;
             }
@@ -292,50 +336,6 @@ impl Bot {
             }
         }
 
-        // Update order
-        if let Some(order) = self.order {
-            match order {
-                Order::MoveToExtendManipulator(pos) => {
-                    if self.pos_angle.pos == pos {
-                        self.order = Some(Order::DoExtendManipulator);
-                    }
-                }
-                Order::DoExtendManipulator => {
-                    self.order = None;
-                }
-                Order::MoveToClone(pos) => {
-                    if self.pos_angle.pos == pos {
-                        self.order = Some(Order::FindMysterious);
-                    }
-                }
-                Order::FindMysterious => unreachable!(),
-                Order::MoveToMysterious(pos) => {
-                    if self.pos_angle.pos == pos {
-                        self.order = Some(Order::DoClone);
-                    }
-                }
-                Order::DoClone => {
-                    self.order = None;
-                }
-                Order::MoveToFastWheel(pos) => {
-                    if self.pos_angle.pos == pos {
-                        self.order = Some(Order::DoFastWheels)
-                    }
-                }
-                Order::DoFastWheels => {
-                    self.order = None;
-                }
-                Order::MoveToDrill(pos) => {
-                    if self.pos_angle.pos == pos {
-                        self.order = Some(Order::DoDrill)
-                    }
-                }
-                Order::DoDrill => {
-                    self.order = None;
-                }
-            }
-        }
-
         // Record action
         if !fast_wheel_second_move {
             if let Action::ExtendManipulator(posdiff) = action {
@@ -346,13 +346,14 @@ impl Bot {
                 self.record.push(action);
             }
         }
+        drilled
     }
 }
 
 #[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
-struct PosAngle {
-    pos: Pos,
-    angle: Angle,
+pub struct PosAngle {
+    pub pos: Pos,
+    pub angle: Angle,
 }
 
 impl std::ops::Add<PosDiff> for PosAngle {
@@ -363,7 +364,7 @@ impl std::ops::Add<PosDiff> for PosAngle {
 }
 
 impl PosAngle {
-    fn new(pos: Pos, angle: Angle) -> PosAngle {
+    pub fn new(pos: Pos, angle: Angle) -> PosAngle {
         PosAngle { pos, angle }
     }
 
@@ -382,7 +383,7 @@ impl PosAngle {
 }
 
 #[derive(Debug, Clone, PartialEq)]
-struct MoveStep {
+pub(crate) struct MoveStep {
     pub pos_angle: PosAngle,
     pub len: usize,
     pub mark_number: usize,
@@ -391,6 +392,53 @@ struct MoveStep {
     pub prev: Option<Rc<MoveStep>>,
 }
 
+// Visited set for BFS over PosAngle; one bit per angle in each cell.
+// Poses are kept apart by whether fast wheels and drill are still active, since a pose
+// reached while they are active does not cover the same pose reached after they run out.
+pub(crate) struct PosAngleSet {
+    angles: Grid<u16>,
+}
+
+impl PosAngleSet {
+    pub(crate) fn new(map: &Map) -> PosAngleSet {
+        PosAngleSet {
+            angles: Grid::new(map.max_x, map.max_y, 0),
+        }
+    }
+
+    fn bit(pos_angle: PosAngle, can_use_fast_wheel: bool, can_use_drill: bool) -> u16 {
+        let mode = can_use_fast_wheel as u16 | (can_use_drill as u16) << 1;
+        1 << (mode * 4 + pos_angle.angle as u16)
+    }
+
+    pub(crate) fn contains(
+        &self,
+        pos_angle: PosAngle,
+        can_use_fast_wheel: bool,
+        can_use_drill: bool,
+    ) -> bool {
+        self.angles[pos_angle.pos] & PosAngleSet::bit(pos_angle, can_use_fast_wheel, can_use_drill)
+            != 0
+    }
+
+    pub(crate) fn insert(
+        &mut self,
+        pos_angle: PosAngle,
+        can_use_fast_wheel: bool,
+        can_use_drill: bool,
+    ) {
+        self.angles[pos_angle.pos] |=
+            PosAngleSet::bit(pos_angle, can_use_fast_wheel, can_use_drill);
+    }
+}
+
+// A move in the search. With fast wheels the bot passes through `via` on the way.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct SearchMove {
+    pub(crate) via: Option<PosAngle>,
+    pub(crate) pos_angle: PosAngle,
+}
+
 impl MoveStep {
     // fn actions(mut step: Rc<MoveStep>) -> Vec<Action> {
     //     let mut actions: Vec<Action> = vec![];
@@ -402,88 +450,250 @@ impl MoveStep {
     //     actions
     // }
 
-    fn first_action(&self) -> Action {
+    pub(crate) fn first_action(&self) -> Action {
         let mut action = None;
         let mut step: &MoveStep = self;
         while let Some(prev_step) = step.prev.as_ref() {
             action = step.prev_action;
-            step = &prev_step.as_ref();
+            step = prev_step.as_ref();
         }
         action.unwrap()
     }
 }
 
+// Length of the straight run of free cells ahead which makes attaching fast wheels worthwhile.
+const FAST_WHEEL_MIN_RUN: usize = 10;
+
+#[derive(Debug, Clone)]
+pub struct StepDelta {
+    pub bot: usize,
+    pub action: Action,
+    pub painted: Vec<Pos>,
+    pub drilled: Vec<Pos>,
+    // Cells the bot stood on during the action, which are two with fast wheels.
+    pub passed: Vec<Pos>,
+    pub cloned: bool,
+    // Boosters picked up on the passed cells.
+    pub picked: Vec<(Pos, BoosterKind)>,
+    // The booster used, with the turn it was usable from.
+    pub used: Option<(BoosterKind, usize)>,
+    // Reservations of the picked boosters, and the plans of other bots which went for them.
+    released: Vec<(Pos, Reservation)>,
+    dropped: Vec<(usize, Plan)>,
+    prev_pos_angle: PosAngle,
+    prev_fast_wheel_timer: usize,
+    prev_drill_timer: usize,
+    prev_plan: Plan,
+    prev_manipulators_len: usize,
+    prev_record_len: usize,
+}
+
+// What a bot does in a turn.
+struct Decision {
+    action: Action,
+    // The booster the bot goes for, which no other bot may take.
+    claim: Option<(Pos, Reservation)>,
+    // The plan the bot takes up, if it changes.
+    plan: Option<Plan>,
+    // Whether the boosters on the map were looked at.
+    read_boosters: bool,
+    // How far from the bot painted cells were looked at, if at all.
+    paint_radius: Option<i32>,
+}
+
+impl Decision {
+    fn new(action: Action) -> Decision {
+        Decision {
+            action,
+            claim: None,
+            plan: None,
+            read_boosters: false,
+            paint_radius: None,
+        }
+    }
+}
+
+// What the bots which already moved in a turn changed.
+#[derive(Default)]
+struct TurnChanges {
+    painted: Vec<Pos>,
+    boosters: bool,
+    walls: bool,
+    // Bots whose plan was dropped as another bot picked up their booster.
+    dropped: Vec<usize>,
+}
+
+impl TurnChanges {
+    // Whether the decision taken at the start of the turn by bot i at the position still holds.
+    fn keep(&self, decision: &Decision, i: usize, pos: Pos) -> bool {
+        if self.walls || (self.boosters && decision.read_boosters) || self.dropped.contains(&i) {
+            return false;
+        }
+        decision.paint_radius.is_none_or(|radius| {
+            self.painted
+                .iter()
+                .all(|cell| (cell.x - pos.x).abs() + (cell.y - pos.y).abs() > radius)
+        })
+    }
+}
+
+#[derive(Clone)]
 pub struct System {
-    map: Map,
-    bots: Vec<Bot>,
-    booster_pos: HashMap<Pos, BoosterKind>,
-    mysterious_pos: HashSet<Pos>,
+    pub(crate) map: Map,
+    pub(crate) bots: Vec<Bot>,
+    // Boosters lying on the map.
+    pub(crate) booster_pos: Grid<Option<BoosterKind>>,
+    pub(crate) mysterious_pos: BitGrid,
+    // Teleport beacons in the order they were installed.
+    pub(crate) beacons: Vec<Pos>,
+    pub(crate) reservations: Reservations,
+    pub(crate) inventory: Inventory,
+    pub(crate) clone_plan: Option<ClonePlan>,
+    pub(crate) trace: Option<Trace>,
+    pub(crate) gain_maps: GainMaps,
+    // solve gives up with Timeout after this.
+    pub(crate) deadline: Option<Instant>,
 }
 
+#[derive(Debug)]
+pub struct Timeout;
+
+impl std::fmt::Display for Timeout {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "timed out")
+    }
+}
+
+impl Fail for Timeout {}
+
 impl System {
+    /// Loads contest/problem/prob-NNN.desc.
     pub fn new(id: u64) -> Result<System> {
-        let task = Task::read_with_id(id)?;
-        let map = Map::new(task);
+        Ok(System::from_task(Task::read_with_id(id)?))
+    }
+
+    /// A simulator at turn 0 of the task, with a single bot.
+    pub fn from_task(task: Task) -> System {
+        System::from_map(Map::new(task))
+    }
+
+    pub(crate) fn from_map(mut map: Map) -> System {
         let bot = Bot::new(map.bot_start_pos);
+        bot.mark_map(&mut map);
 
-        let booster_pos = map
-            .boosters
-            .iter()
-            .filter(|booster| booster.kind != BoosterKind::Mysterious)
-            .map(|booster| (booster.pos, booster.kind))
-            .collect();
-        let mysterious_pos = map
-            .boosters
-            .iter()
-            .filter(|booster| booster.kind == BoosterKind::Mysterious)
-            .map(|booster| booster.pos)
-            .collect();
-        Ok(System {
+        let mut booster_pos = Grid::new(map.max_x, map.max_y, None);
+        let mut mysterious_pos = BitGrid::new(map.max_x, map.max_y);
+        for booster in &map.boosters {
+            if booster.kind == BoosterKind::Mysterious {
+                mysterious_pos.insert(booster.pos);
+            } else {
+                booster_pos[booster.pos] = Some(booster.kind);
+            }
+        }
+        let bots = vec![bot];
+        System {
+            gain_maps: GainMaps::new(&bots, &map),
             map,
-            bots: vec![bot],
+            bots,
             booster_pos,
             mysterious_pos,
-        })
+            beacons: vec![],
+            reservations: Reservations::default(),
+            inventory: Inventory::default(),
+            clone_plan: None,
+            trace: None,
+            deadline: None,
+        }
     }
 
+    // Whether a booster of the kind lies on the map and no bot goes for it.
     fn has_booster(&self, booster: BoosterKind) -> bool {
-        self.booster_pos.iter().any(|(_, kind)| *kind == booster)
+        self.map.boosters.iter().any(|b| {
+            b.kind == booster
+                && self.booster_pos[b.pos] == Some(booster)
+                && !self.reservations.is_reserved(b.pos)
+        })
     }
 
-    fn order_swap(&mut self, bot_a: usize, bot_b: usize) {
-        match (self.bots[bot_a].order, self.bots[bot_b].order) {
-            (Some(Order::MoveToMysterious(pos)), None) => {
-                if self.distance(self.bots[bot_a].pos_angle.pos, pos).unwrap()
+    fn plan_swap(&mut self, bot_a: usize, bot_b: usize) {
+        if let [Goal::GoTo(pos), Goal::Activate(BoosterKind::Cloning)] =
+            *self.bots[bot_a].plan.goals()
+        {
+            if self.bots[bot_b].plan.is_empty()
+                && self.distance(self.bots[bot_a].pos_angle.pos, pos).unwrap()
                     > self.distance(self.bots[bot_b].pos_angle.pos, pos).unwrap()
-                {
-                    self.bots[bot_a].order = None;
-                    self.bots[bot_b].order = Some(Order::MoveToMysterious(pos));
-                }
+            {
+                self.bots[bot_b].plan = std::mem::take(&mut self.bots[bot_a].plan);
             }
-            (Some(Order::MoveToClone(pos)), None) => {
-                if self.distance(self.bots[bot_a].pos_angle.pos, pos).unwrap()
-                    > self.distance(self.bots[bot_b].pos_angle.pos, pos).unwrap()
-                {
-                    self.bots[bot_a].order = None;
-                    self.bots[bot_b].order = Some(Order::MoveToClone(pos));
+        }
+    }
+
+    // Hands each booster a bot goes for over to an idle bot which gets there earlier, and
+    // updates when it is expected to be picked up.
+    fn reassign_boosters(&mut self) {
+        let reservations = self.reservations.iter().collect::<Vec<_>>();
+        for (pos, reservation) in reservations {
+            if self.bots.iter().all(|bot| !bot.plan.is_empty()) {
+                return;
+            }
+            let dist = distances(&self.map, pos);
+            let eta = |bot: &Bot| bot.time().saturating_add(dist[bot.pos_angle.pos]);
+            let closer = (0..self.bots.len())
+                .filter(|j| self.bots[*j].plan.is_empty())
+                .map(|j| (eta(&self.bots[j]), j))
+                .min()
+                .filter(|(closer_eta, _)| *closer_eta < eta(&self.bots[reservation.bot]));
+            let reservation = self.reservations.get_mut(pos).unwrap();
+            match closer {
+                Some((closer_eta, j)) => {
+                    self.bots[j].plan = std::mem::take(&mut self.bots[reservation.bot].plan);
+                    reservation.bot = j;
+                    reservation.eta = closer_eta;
                 }
+                None => reservation.eta = eta(&self.bots[reservation.bot]),
             }
-            _ => {}
         }
     }
 
     pub fn solve(&mut self) -> Result<()> {
-        self.bots[0].mark_map(&mut self.map);
-
         while self.map.empty_cell_count != 0 {
-            for i in 0..self.bots.len() {
-                for j in 0..self.bots.len() {
-                    self.order_swap(i, j);
+            if self
+                .deadline
+                .is_some_and(|deadline| Instant::now() >= deadline)
+            {
+                return Err(Timeout.into());
+            }
+            // A clone plan already decides which bot does what.
+            if self.clone_plan.is_none() {
+                for i in 0..self.bots.len() {
+                    for j in 0..self.bots.len() {
+                        self.plan_swap(i, j);
+                    }
                 }
+                self.reassign_boosters();
             }
 
             debug!("empty cell: {}", self.map.empty_cell_count);
-            for i in 0..self.bots.len() {
+            // Every bot decides against the state at the start of the turn, in parallel. Bots
+            // which are given a clone task first decide in turn. With a single thread, deciding
+            // again the decisions which went stale would only cost time.
+            let decisions = if self.bots.len() > 1 && rayon::current_num_threads() > 1 {
+                let system = &*self;
+                (0..self.bots.len())
+                    .into_par_iter()
+                    .map(|i| {
+                        if system.bots[i].plan.is_empty() && system.clone_plan.is_some() {
+                            None
+                        } else {
+                            system.decide(i).ok()
+                        }
+                    })
+                    .collect::<Vec<_>>()
+            } else {
+                self.bots.iter().map(|_| None).collect()
+            };
+            let mut changes = TurnChanges::default();
+            for (i, speculated) in decisions.into_iter().enumerate() {
                 if self.map.empty_cell_count == 0 {
                     break;
                 }
@@ -494,134 +704,576 @@ impl System {
                     self.bots[i].pos_angle.pos
                 );
 
-                // Find and apply action
-                let action = {
-                    if let Some(order) = self.bots[i].order {
-                        match order {
-                            Order::MoveToExtendManipulator(pos) => {
-                                self.move_to_action(&self.bots[i], pos)
-                            }
-                            Order::DoExtendManipulator => Ok(Action::ExtendManipulator(
-                                self.bots[i].find_extend_manipulator_position(),
-                            )),
-                            Order::MoveToClone(pos) => self.move_to_action(&self.bots[i], pos),
-                            Order::FindMysterious => {
-                                let step = self.find_mysterious(&self.bots[i])?;
-                                self.bots[i].order =
-                                    Some(Order::MoveToMysterious(step.pos_angle.pos));
-                                Ok(step.first_action())
-                            }
-                            Order::MoveToMysterious(pos) => self.move_to_action(&self.bots[i], pos),
-                            Order::DoClone => Ok(Action::Cloning),
-                            Order::MoveToFastWheel(pos) => self.move_to_action(&self.bots[i], pos),
-                            Order::DoFastWheels => Ok(Action::AttachFastWheels),
-                            Order::MoveToDrill(pos) => self.move_to_action(&self.bots[i], pos),
-                            Order::DoDrill => Ok(Action::AttachDrill),
-                        }
-                    } else if let Ok(step) = self.find_booster_near(
-                        &self.bots[i],
-                        // TODO: Support Drill
-                        // &[BoosterKind::FastWheels, BoosterKind::Drill],
-                        &[BoosterKind::FastWheels],
-                        5,
-                    ) {
-                        let booster = self.booster_pos.remove(&step.pos_angle.pos).unwrap();
-                        match booster {
-                            BoosterKind::FastWheels => {
-                                self.bots[i].order =
-                                    Some(Order::MoveToFastWheel(step.pos_angle.pos));
-                            }
-                            BoosterKind::Drill => {
-                                self.bots[i].order = Some(Order::MoveToDrill(step.pos_angle.pos));
-                            }
-                            _ => unreachable!(),
-                        }
-                        Ok(step.first_action())
-                    } else if let Ok(step) = self.find_booster(
-                        &self.bots[i],
-                        &[BoosterKind::ExtendManipulator, BoosterKind::Cloning],
-                    ) {
-                        debug!("> Found booster");
-                        // Remove it here.
-                        // It's okay for other bots picked up earlier than this bot by accident. That should not have any bad affect
-                        // bacause boosters are shared.
-                        let booster = self.booster_pos.remove(&step.pos_angle.pos).unwrap();
-                        match booster {
-                            BoosterKind::ExtendManipulator => {
-                                self.bots[i].order =
-                                    Some(Order::MoveToExtendManipulator(step.pos_angle.pos));
-                                Ok(step.first_action())
-                            }
-                            BoosterKind::Cloning => {
-                                self.bots[i].order = Some(Order::MoveToClone(step.pos_angle.pos));
-                                Ok(step.first_action())
-                                // self.bots[i].picking_booster = Some(booster)
-                            }
-                            _ => unreachable!(),
-                        }
-                    } else {
-                        match self.find_mark_move(&self.bots[i]) {
-                            Ok(step) => Ok(step.first_action()),
-                            Err(_) => {
-                                assert!(self.bots[i].fast_wheel_timer > 0);
-                                Ok(Action::DoNothing)
+                // The boosters the other bots see as unclaimed depend on what this one holds.
+                let held = self.bots[i].plan.holds();
+                if self.bots[i].plan.is_empty() && self.assign_clone_task(i) {
+                    changes.boosters = true;
+                }
+
+                // A decision is taken again if the bots before this one changed what it saw,
+                // so that the result is the same as deciding one bot after another.
+                let decision = match speculated {
+                    Some(decision) if changes.keep(&decision, i, self.bots[i].pos_angle.pos) => {
+                        decision
+                    }
+                    _ => self.decide(i)?,
+                };
+                if let Some((pos, reservation)) = decision.claim {
+                    self.reservations.reserve(pos, reservation);
+                    changes.boosters = true;
+                }
+                if let Some(plan) = decision.plan {
+                    // A booster the bot no longer goes for is free for the others.
+                    for goal in self.bots[i].plan.goals() {
+                        if let Goal::PickUp(pos) = goal {
+                            if !plan.goals().contains(goal) {
+                                self.reservations.release(*pos);
+                                changes.boosters = true;
                             }
                         }
                     }
-                }?;
+                    self.bots[i].plan = plan;
+                }
 
                 debug!(
                     ">> turn: {}, fast_wheel_timer: {}, acton: {:?}",
                     self.bots[i].record.len(),
                     self.bots[i].fast_wheel_timer,
-                    action
+                    decision.action
                 );
 
-                // Save this here because apply_action can change timer value
-                let can_use_fast_wheel_in_this_turn = self.bots[i].fast_wheel_timer > 0;
-                let can_use_drill_in_this_turn = self.bots[i].drill_timer > 0;
-;
-                if can_use_fast_wheel_in_this_turn && action.is_move() {
-                    self.bots[i].apply_action(action, &mut self.map, false);
-                    self.bots[i].mark_map(&mut self.map);
-                    self.bots[i].apply_action(action, &mut self.map, true);
-                    self.bots[i].mark_map(&mut self.map);
-                } else {
-                    self.bots[i].apply_action(action, &mut self.map, false);
-                    self.bots[i].mark_map(&mut self.map);
+                let delta = self.step(i, decision.action);
+                if delta.cloned {
+                    if let Some(plan) = self.clone_plan.as_mut() {
+                        plan.spawned(i, self.bots.len() - 1);
+                    }
                 }
+                changes.painted.extend(delta.painted);
+                changes.walls |= !delta.drilled.is_empty();
+                changes.boosters |= !delta.picked.is_empty()
+                    || delta.used.is_some()
+                    || self.bots[i].plan.holds() != held;
+                changes
+                    .dropped
+                    .extend(delta.dropped.iter().map(|(bot, _)| *bot));
+
+                debug!("bot's pos: {:?}", self.bots[i].pos_angle.pos);
+                debug!("empty cell count: {:?}", self.map.empty_cell_count);
+            }
+        }
+        Ok(())
+    }
+
+    // Gives the bot its next planned clone task. Tasks whose booster was taken some other way
+    // are dropped. Returns whether a task was given.
+    fn assign_clone_task(&mut self, i: usize) -> bool {
+        let plan = match self.clone_plan.as_mut() {
+            Some(plan) => plan,
+            None => return false,
+        };
+        while let Some(task) = plan.next_task(i) {
+            // A booster is only picked up by moving onto it, so not one the bot stands on.
+            let pos = self.bots[i].pos_angle.pos;
+            if self.booster_pos[task.booster] != Some(BoosterKind::Cloning)
+                || self.reservations.is_reserved(task.booster)
+                || pos == task.booster
+            {
+                continue;
+            }
+            self.reservations.reserve(
+                task.booster,
+                Reservation {
+                    bot: i,
+                    kind: BoosterKind::Cloning,
+                    eta: self.bots[i].time() + distances(&self.map, task.booster)[pos],
+                },
+            );
+            self.bots[i].plan = Plan::new(vec![
+                Goal::PickUp(task.booster),
+                Goal::GoTo(task.spawn),
+                Goal::Activate(BoosterKind::Cloning),
+            ]);
+            return true;
+        }
+        false
+    }
+
+    // The action of bot i in this turn, and the plan it takes up with it.
+    fn decide(&self, i: usize) -> Result<Decision> {
+        let bot = &self.bots[i];
+        if bot.plan.is_empty() {
+            return self.decide_idle(i);
+        }
+        if let Some(decision) = self.follow_plan(bot, &bot.plan) {
+            return Ok(decision);
+        }
+        // The plan cannot be carried out any more, so the bot starts over.
+        let decision = self.decide_idle(i)?;
+        Ok(Decision {
+            plan: Some(decision.plan.unwrap_or_default()),
+            ..decision
+        })
+    }
 
-                // Decrement only when they had positive values before apply action
-                if can_use_fast_wheel_in_this_turn {
-                    self.bots[i].fast_wheel_timer -= 1;
+    // The action towards the first goal of the plan, with the plan if it is not the bot's. Goals
+    // with nothing to do are skipped, a goal meeting the precondition of the first one is put
+    // before it, and the bot stays in place while it waits. None if the plan has to be dropped.
+    fn follow_plan(&self, bot: &Bot, plan: &Plan) -> Option<Decision> {
+        let mut plan = plan.clone();
+        // Whether a sweep looked at the painted cells of its region, however far from the bot.
+        let mut swept = false;
+        loop {
+            let goal = plan.first()?;
+            swept |= matches!(goal, Goal::Sweep(_));
+            let decision = match goal.precondition(self, bot) {
+                Precondition::Met => Decision::new(goal.action(self, bot)?),
+                Precondition::Wait => Decision {
+                    read_boosters: true,
+                    paint_radius: Some(bot.reach()),
+                    ..Decision::new(self.wait_action(bot))
+                },
+                Precondition::Skip => {
+                    plan.skip_first();
+                    continue;
                 }
-                if can_use_drill_in_this_turn {
-                    self.bots[i].drill_timer -= 1;
+                Precondition::Needs(first) => {
+                    plan = plan.prepend(first);
+                    continue;
                 }
+                Precondition::Replan => return None,
+            };
+            return Some(Decision {
+                plan: if plan == bot.plan { None } else { Some(plan) },
+                paint_radius: if swept {
+                    Some(i32::MAX)
+                } else {
+                    decision.paint_radius
+                },
+                ..decision
+            });
+        }
+    }
 
-                debug!("bot's pos: {:?}", self.bots[i].pos_angle.pos);
-                debug!("empty cell count: {:?}", self.map.empty_cell_count);
+    // Turns where that paints, and does nothing otherwise.
+    fn wait_action(&self, bot: &Bot) -> Action {
+        [Action::TurnClockWise, Action::TurnCounterClockWise]
+            .iter()
+            .map(|action| {
+                let pos_angle = bot.pos_angle.apply_action(*action);
+                let painted = bot
+                    .manipulators
+                    .iter()
+                    .filter(|manipulator| manipulator.can_mark(pos_angle, &self.map))
+                    .count();
+                (painted, *action)
+            })
+            .filter(|(painted, _)| *painted > 0)
+            .max_by_key(|(painted, _)| *painted)
+            .map_or(Action::DoNothing, |(_, action)| action)
+    }
 
-                // System wide effects
-                if let Action::Cloning = action {
-                    self.bots.push(Bot::new(self.bots[i].pos_angle.pos));
+    // The action of bot i without a plan, which paints unless a booster is worth going for.
+    fn decide_idle(&self, i: usize) -> Result<Decision> {
+        let bot = &self.bots[i];
+        // Boosters in the inventory which no bot is going to use.
+        for kind in &[BoosterKind::ExtendManipulator, BoosterKind::Cloning] {
+            if self.unclaimed(*kind, bot.time()) == 0 {
+                continue;
+            }
+            if let Some(decision) = self.follow_plan(bot, &Plan::new(vec![Goal::Activate(*kind)])) {
+                return Ok(Decision {
+                    read_boosters: true,
+                    ..decision
+                });
+            }
+        }
+
+        let claim = |step: Rc<MoveStep>| {
+            let pos = step.pos_angle.pos;
+            let kind = self.booster_pos[pos].unwrap();
+            Decision {
+                claim: Some((
+                    pos,
+                    Reservation {
+                        bot: i,
+                        kind,
+                        eta: bot.time() + step.len,
+                    },
+                )),
+                plan: Some(Plan::fetch(pos, kind)),
+                read_boosters: true,
+                ..Decision::new(step.first_action())
+            }
+        };
+        if let Ok(step) = self.find_booster_near(
+            bot,
+            // TODO: Support Drill
+            // &[BoosterKind::FastWheels, BoosterKind::Drill],
+            &[BoosterKind::FastWheels],
+            5,
+        ) {
+            return Ok(claim(step));
+        }
+        if let Ok(step) = self.find_booster(
+            bot,
+            if self.clone_plan.is_some() {
+                &[BoosterKind::ExtendManipulator]
+            } else {
+                &[BoosterKind::ExtendManipulator, BoosterKind::Cloning]
+            },
+        ) {
+            debug!("> Found booster");
+            // Another bot may pass over it first, which is as good since boosters are shared.
+            return Ok(claim(step));
+        }
+
+        let step = match self.find_mark_move(bot) {
+            Ok(step) => step,
+            // Every move may run past the cells left to paint, so the bot waits for the fast
+            // wheels to wear off. Cells painted meanwhile do not give it a target.
+            Err(_) if bot.fast_wheel_timer > 0 => {
+                return Ok(Decision {
+                    read_boosters: true,
+                    ..Decision::new(Action::DoNothing)
+                })
+            }
+            Err(e) => return Err(e),
+        };
+        let action = step.first_action();
+        let action = if self.should_attach_fast_wheels(bot, action) {
+            Action::AttachFastWheels
+        } else {
+            action
+        };
+        // The search only looks at poses it reaches within one more move, two cells each with
+        // fast wheels, and at the cells their manipulators and the cells next to them.
+        Ok(Decision {
+            read_boosters: true,
+            paint_radius: Some(2 * (step.len as i32 + 1) + bot.reach() + 1),
+            ..Decision::new(action)
+        })
+    }
+
+    pub fn map(&self) -> &Map {
+        &self.map
+    }
+
+    pub fn bots(&self) -> &[Bot] {
+        &self.bots
+    }
+
+    /// The booster lying on the cell, if not picked up yet.
+    pub fn booster_at(&self, pos: Pos) -> Option<BoosterKind> {
+        if self.mysterious_pos.contains(pos) {
+            Some(BoosterKind::Mysterious)
+        } else {
+            self.booster_pos[pos]
+        }
+    }
+
+    /// Checks that bot i can take the action under the contest rules. A booster must be in the
+    /// inventory since before this turn. A manipulator is relative to the bot, as `step` takes it.
+    pub fn check_action(&self, i: usize, action: Action) -> Result<()> {
+        use Action::*;
+        let bot = self
+            .bots
+            .get(i)
+            .ok_or_else(|| failure::format_err!("no bot {}", i))?;
+        match action {
+            MoveUp | MoveDown | MoveLeft | MoveRight => {
+                let pos = bot.pos_angle.apply_action(action).pos;
+                if !self.map.is_in_range(pos) {
+                    return Err(failure::err_msg("moving out of the map"));
+                }
+                if self.map.is_wall(pos) && bot.drill_timer == 0 {
+                    return Err(failure::err_msg("moving into a wall"));
+                }
+            }
+            ExtendManipulator(posdiff) => {
+                let attached = bot
+                    .manipulators
+                    .iter()
+                    .map(|manipulator| manipulator.posdiff)
+                    .collect::<Vec<_>>();
+                if attached.contains(&posdiff)
+                    || !attached.iter().any(|diff| {
+                        (diff.dx - posdiff.dx).abs() + (diff.dy - posdiff.dy).abs() == 1
+                    })
+                {
+                    return Err(failure::err_msg(
+                        "a new manipulator must be next to an attached one",
+                    ));
+                }
+            }
+            Cloning => {
+                if !self.mysterious_pos.contains(bot.pos_angle.pos) {
+                    return Err(failure::err_msg("cloning off a mysterious point"));
+                }
+            }
+            InstallBeacon => {
+                let pos = bot.pos_angle.pos;
+                if self.mysterious_pos.contains(pos) || self.beacons.contains(&pos) {
+                    return Err(failure::err_msg(
+                        "a beacon can not be installed on a mysterious point or another beacon",
+                    ));
                 }
             }
+            Shift(pos) => {
+                if !self.beacons.contains(&pos) {
+                    return Err(failure::format_err!("no beacon at {:?}", pos));
+                }
+            }
+            DoNothing | TurnClockWise | TurnCounterClockWise | AttachFastWheels | AttachDrill => {}
+        }
+        if let Some(kind) = action.booster() {
+            if self.inventory.count(kind, bot.time()) == 0 {
+                return Err(failure::format_err!("no {} booster to use", kind));
+            }
         }
         Ok(())
     }
 
-    fn move_to_action(&self, bot: &Bot, goal: Pos) -> Result<Action> {
+    /// `step` for actions which may not be allowed, which fails instead of panicking.
+    pub fn try_step(&mut self, i: usize, action: Action) -> Result<StepDelta> {
+        self.check_action(i, action)?;
+        Ok(self.step(i, action))
+    }
+
+    /// Applies one action of the given bot and returns what changed, so that the caller can
+    /// roll it back with `undo`. The action must be allowed, see `try_step`.
+    pub fn step(&mut self, i: usize, action: Action) -> StepDelta {
+        let bot = &self.bots[i];
+        let mut delta = StepDelta {
+            bot: i,
+            action,
+            painted: vec![],
+            drilled: vec![],
+            passed: vec![],
+            cloned: false,
+            picked: vec![],
+            used: None,
+            released: vec![],
+            dropped: vec![],
+            prev_pos_angle: bot.pos_angle,
+            prev_fast_wheel_timer: bot.fast_wheel_timer,
+            prev_drill_timer: bot.drill_timer,
+            prev_plan: bot.plan.clone(),
+            prev_manipulators_len: bot.manipulators.len(),
+            prev_record_len: bot.record.len(),
+        };
+
+        let turn = self.bots[i].time();
+        if let Some(kind) = action.booster() {
+            let usable_from = self
+                .inventory
+                .take(kind, turn)
+                .unwrap_or_else(|| panic!("no {} booster to use", kind));
+            delta.used = Some((kind, usable_from));
+        }
+
+        // Save this here because apply_action can change timer value
+        let can_use_fast_wheel_in_this_turn = self.bots[i].fast_wheel_timer > 0;
+        let can_use_drill_in_this_turn = self.bots[i].drill_timer > 0;
+
+        let moves: &[bool] = if can_use_fast_wheel_in_this_turn && action.is_move() {
+            &[false, true]
+        } else {
+            &[false]
+        };
+        for fast_wheel_second_move in moves {
+            let drilled = self.bots[i].apply_action(action, &mut self.map, *fast_wheel_second_move);
+            delta.drilled.extend(drilled);
+            let painted = self.bots[i].mark_map(&mut self.map);
+            delta.painted.extend(painted);
+            delta.passed.push(self.bots[i].pos_angle.pos);
+        }
+        let pos = self.bots[i].pos_angle.pos;
+        self.bots[i].plan.update(&delta.passed, pos, action);
+        self.pick_up_boosters(&mut delta, turn);
+
+        // Decrement only when they had positive values before apply action
+        if can_use_fast_wheel_in_this_turn {
+            self.bots[i].fast_wheel_timer -= 1;
+        }
+        if can_use_drill_in_this_turn {
+            self.bots[i].drill_timer -= 1;
+        }
+
+        if self.trace.is_some() {
+            self.record_trace(&delta);
+        }
+
+        // System wide effects
+        if let Action::Cloning = action {
+            let mut bot = Bot::new(self.bots[i].pos_angle.pos);
+            bot.spawn_turn = self.bots[i].time();
+            self.bots.push(bot);
+            delta.cloned = true;
+        }
+        if let Action::InstallBeacon = action {
+            self.beacons.push(pos);
+        }
+        let extended = self.bots[i].manipulators.len() != delta.prev_manipulators_len;
+        self.update_gain_maps(&delta, extended);
+        delta
+    }
+
+    // Boosters are picked up into the inventory by whichever bot passes over them in the turn.
+    // A bot which went for one another bot picked up is left without a plan.
+    fn pick_up_boosters(&mut self, delta: &mut StepDelta, turn: usize) {
+        for pos in &delta.passed {
+            let kind = match self.booster_pos[*pos].take() {
+                Some(kind) => kind,
+                None => continue,
+            };
+            delta.picked.push((*pos, kind));
+            self.inventory.add(kind, turn + 1);
+            if let Some(reservation) = self.reservations.release(*pos) {
+                delta.released.push((*pos, reservation));
+                if reservation.bot != delta.bot {
+                    let plan = std::mem::take(&mut self.bots[reservation.bot].plan);
+                    delta.dropped.push((reservation.bot, plan));
+                }
+            }
+        }
+    }
+
+    // After a step or its undo, which changed the cells of the delta, and the manipulators of
+    // the bot if extended.
+    fn update_gain_maps(&mut self, delta: &StepDelta, extended: bool) {
+        self.gain_maps.update(&delta.painted, &self.map);
+        self.gain_maps.update(&delta.drilled, &self.map);
+        if delta.cloned || extended {
+            self.gain_maps.sync(&self.bots, &self.map);
+        }
+    }
+
+    pub fn set_deadline(&mut self, deadline: Instant) {
+        self.deadline = Some(deadline);
+    }
+
+    pub fn enable_trace(&mut self) {
+        self.trace = Some(Trace::default());
+    }
+
+    fn record_trace(&mut self, delta: &StepDelta) {
+        let bot = &self.bots[delta.bot];
+        let event = TraceEvent {
+            turn: bot.time(),
+            bot: delta.bot,
+            x: bot.pos_angle.pos.x,
+            y: bot.pos_angle.pos.y,
+            angle: match bot.pos_angle.angle {
+                Angle::A0 => 0,
+                Angle::A90 => 90,
+                Angle::A180 => 180,
+                Angle::A270 => 270,
+            },
+            action: bot.record.last().unwrap().to_string(),
+            painted: delta.painted.len(),
+            plan: if bot.plan.is_empty() {
+                None
+            } else {
+                Some(format!("{:?}", bot.plan.goals()))
+            },
+            fast_wheel_timer: bot.fast_wheel_timer,
+            drill_timer: bot.drill_timer,
+            empty: self.map.empty_cell_count,
+            picked: vec![],
+        };
+        self.trace.as_mut().unwrap().record(event, &delta.picked);
+    }
+
+    /// Rolls back a step. Deltas must be undone in the reverse order of `step`.
+    pub fn undo(&mut self, mut delta: StepDelta) {
+        if let Some(trace) = &mut self.trace {
+            trace.undo();
+        }
+        if delta.cloned {
+            assert!(self.bots.len() > 1);
+            self.bots.pop();
+        }
+        if let Action::InstallBeacon = delta.action {
+            self.beacons.pop();
+        }
+        for pos in delta.painted.iter().rev() {
+            self.map.unmark_pos(*pos);
+        }
+        for pos in delta.drilled.iter().rev() {
+            self.map.undo_drill(*pos);
+        }
+        for (bot, plan) in delta.dropped.iter().rev() {
+            self.bots[*bot].plan = plan.clone();
+        }
+        for (pos, reservation) in &delta.released {
+            self.reservations.reserve(*pos, *reservation);
+        }
+        let turn = delta.prev_record_len + self.bots[delta.bot].spawn_turn;
+        for (pos, kind) in &delta.picked {
+            self.booster_pos[*pos] = Some(*kind);
+            self.inventory.remove(*kind, turn + 1);
+        }
+        if let Some((kind, usable_from)) = delta.used {
+            self.inventory.add(kind, usable_from);
+        }
+        let bot = &mut self.bots[delta.bot];
+        bot.pos_angle = delta.prev_pos_angle;
+        bot.fast_wheel_timer = delta.prev_fast_wheel_timer;
+        bot.drill_timer = delta.prev_drill_timer;
+        bot.plan = std::mem::take(&mut delta.prev_plan);
+        let extended = bot.manipulators.len() != delta.prev_manipulators_len;
+        bot.manipulators.truncate(delta.prev_manipulators_len);
+        bot.record.truncate(delta.prev_record_len);
+        self.update_gain_maps(&delta, extended);
+    }
+
+    // Fast wheels pay off on long straight runs. Elsewhere the double move overshoots.
+    fn should_attach_fast_wheels(&self, bot: &Bot, action: Action) -> bool {
+        if self.unclaimed(BoosterKind::FastWheels, bot.time()) == 0
+            || bot.fast_wheel_timer > 0
+            || !action.is_move()
+        {
+            return false;
+        }
+        let mut run = 0;
+        let mut pos_angle = bot.pos_angle.apply_action(action);
+        while run < FAST_WHEEL_MIN_RUN && self.map.is_free(pos_angle.pos) {
+            run += 1;
+            pos_angle = pos_angle.apply_action(action);
+        }
+        run >= FAST_WHEEL_MIN_RUN
+    }
+
+    // Boosters of the kind in the inventory usable at the turn, less those the bots picked up
+    // for their plans.
+    fn unclaimed(&self, kind: BoosterKind, turn: usize) -> usize {
+        let claimed = self
+            .bots
+            .iter()
+            .filter(|bot| bot.plan.holds() == Some(kind))
+            .count();
+        self.inventory.count(kind, turn).saturating_sub(claimed)
+    }
+
+    pub(crate) fn move_to_action(&self, bot: &Bot, goal: Pos) -> Result<Action> {
         Ok(self.find_move_to(bot, |pos| pos == goal)?.first_action())
     }
 
+    // The first move towards the nearest cell of the region left to paint.
+    pub(crate) fn sweep_action(&self, bot: &Bot, region: Region) -> Result<Action> {
+        Ok(self
+            .find_move_to(bot, |pos| region.contains(pos) && self.map.is_empty(pos))?
+            .first_action())
+    }
+
     fn find_booster(&self, bot: &Bot, boosters: &[BoosterKind]) -> Result<Rc<MoveStep>> {
         if boosters.iter().all(|booster| !self.has_booster(*booster)) {
             Err(failure::err_msg("booster is no longer available"))
         } else {
             self.find_move_to(bot, |pos| {
-                if let Some(found_booster) = self.booster_pos.get(&pos) {
+                if let Some(Some(found_booster)) = self.booster_pos.get(pos) {
                     boosters.iter().any(|booster| booster == found_booster)
+                        && !self.reservations.is_reserved(pos)
                 } else {
                     false
                 }
@@ -643,12 +1295,12 @@ impl System {
         }
     }
 
-    fn find_mysterious(&self, bot: &Bot) -> Result<Rc<MoveStep>> {
-        self.find_move_to(bot, |pos| self.mysterious_pos.contains(&pos))
+    pub(crate) fn find_mysterious(&self, bot: &Bot) -> Result<Rc<MoveStep>> {
+        self.find_move_to(bot, |pos| self.mysterious_pos.contains(pos))
     }
 
     fn distance(&self, a: Pos, b: Pos) -> Result<usize> {
-        let mut visited = HashSet::new();
+        let mut visited = BitGrid::new(self.map.max_x, self.map.max_y);
         visited.insert(a);
 
         let mut q = VecDeque::new();
@@ -657,10 +1309,8 @@ impl System {
             if pos == b {
                 return Ok(len);
             }
-            for d in PosDiff::gen_all_diff() {
-                let next = pos + *d;
-                if !visited.contains(&next) {
-                    visited.insert(next);
+            for next in self.map.walls.neighbours(pos) {
+                if visited.insert(next) {
                     q.push_back((next, len + 1));
                 }
             }
@@ -668,7 +1318,45 @@ impl System {
         Err(failure::err_msg("can not reach b"))
     }
 
-    fn find_move_to<P>(&self, bot: &Bot, predicate: P) -> Result<Rc<MoveStep>>
+    // Where the bot ends up after the action in the search, following the contest rules for fast
+    // wheels: the second step is skipped when it would hit a wall without a drill or leave the
+    // map. None if the action is not allowed.
+    pub(crate) fn next_move(
+        &self,
+        pos_angle: PosAngle,
+        action: Action,
+        can_use_drill: bool,
+        can_use_fast_wheel: bool,
+    ) -> Option<SearchMove> {
+        let next_pos_angle = pos_angle.apply_action(action);
+        if !self.map.is_in_range(next_pos_angle.pos) {
+            return None;
+        }
+        if !can_use_drill && !self.map.is_free(next_pos_angle.pos) {
+            return None;
+        }
+        let single = SearchMove {
+            via: None,
+            pos_angle: next_pos_angle,
+        };
+        if can_use_fast_wheel && action.is_move() {
+            let next_next_pos_angle = next_pos_angle.apply_action(action);
+            if !self.map.is_in_range(next_next_pos_angle.pos) {
+                Some(single)
+            } else if self.map.is_free(next_next_pos_angle.pos) || can_use_drill {
+                Some(SearchMove {
+                    via: Some(next_pos_angle),
+                    pos_angle: next_next_pos_angle,
+                })
+            } else {
+                Some(single)
+            }
+        } else {
+            Some(single)
+        }
+    }
+
+    pub(crate) fn find_move_to<P>(&self, bot: &Bot, predicate: P) -> Result<Rc<MoveStep>>
     where
         P: Fn(Pos) -> bool,
     {
@@ -682,8 +1370,8 @@ impl System {
             prev: None,
         }));
 
-        let mut visited = HashSet::new();
-        visited.insert(bot.pos_angle);
+        let mut visited = PosAngleSet::new(&self.map);
+        visited.insert(bot.pos_angle, bot.fast_wheel_timer > 0, bot.drill_timer > 0);
 
         use Action::*;
         while let Some(current_step) = q.pop_front() {
@@ -691,28 +1379,18 @@ impl System {
             let can_use_fast_wheel = bot.fast_wheel_timer > current_step.len;
 
             for action in &[MoveUp, MoveDown, MoveLeft, MoveRight] {
-                // TODO: Refactor with xxx.
-                let next_pos_angle = {
-                    let next_pos_angle = current_step.pos_angle.apply_action(*action);
-                    if !self.map.is_in_range(next_pos_angle.pos) {
-                        continue;
-                    }
-                    if !can_use_drill && !self.map.is_free(next_pos_angle.pos) {
-                        continue;
-                    }
-                    if can_use_fast_wheel && action.is_move() {
-                        let next_next_pos_angle = next_pos_angle.apply_action(*action);
-                        if !self.map.is_in_range(next_next_pos_angle.pos) {
-                            next_pos_angle
-                        } else if self.map.is_free(next_next_pos_angle.pos) || can_use_drill {
-                            next_next_pos_angle
-                        } else {
-                            next_pos_angle
-                        }
-                    } else {
-                        next_pos_angle
-                    }
+                let next_move = match self.next_move(
+                    current_step.pos_angle,
+                    *action,
+                    can_use_drill,
+                    can_use_fast_wheel,
+                ) {
+                    Some(next_move) => next_move,
+                    None => continue,
                 };
+                let next_pos_angle = next_move.pos_angle;
+                let next_len = current_step.len + 1;
+                let next_mode = (bot.fast_wheel_timer > next_len, bot.drill_timer > next_len);
 
                 let next = Rc::new(MoveStep {
                     pos_angle: next_pos_angle,
@@ -725,8 +1403,8 @@ impl System {
                 if predicate(next_pos_angle.pos) {
                     return Ok(next);
                 }
-                if !visited.contains(&next_pos_angle) {
-                    visited.insert(next_pos_angle);
+                if !visited.contains(next_pos_angle, next_mode.0, next_mode.1) {
+                    visited.insert(next_pos_angle, next_mode.0, next_mode.1);
                     q.push_back(next);
                 }
             }
@@ -736,7 +1414,7 @@ impl System {
         ))
     }
 
-    fn find_mark_move(&self, bot: &Bot) -> Result<Rc<MoveStep>> {
+    pub(crate) fn find_mark_move(&self, bot: &Bot) -> Result<Rc<MoveStep>> {
         use Action::*;
 
         // debug!("turn: {}, bot.pos: {:?}", self.record.len(), self.bot.pos);
@@ -750,10 +1428,15 @@ impl System {
             prev: None,
         }));
 
-        let mut visited = HashSet::new();
-        visited.insert(bot.pos_angle);
+        let mut visited = PosAngleSet::new(&self.map);
+        visited.insert(bot.pos_angle, bot.fast_wheel_timer > 0, bot.drill_timer > 0);
 
         let mut best: Option<Rc<MoveStep>> = None;
+        let gain_map = self
+            .gain_maps
+            .get(&bot.manipulators)
+            .expect("no gain map for the bot");
+        let visibility = gain_map.visibility();
 
         while let Some(current_step) = q.pop_front() {
             let can_use_drill = bot.drill_timer > current_step.len;
@@ -793,40 +1476,34 @@ impl System {
                 ],
             };
             for action in &actions {
-                let next_pos_angle = {
-                    let next_pos_angle = current_step.pos_angle.apply_action(*action);
-                    if !self.map.is_in_range(next_pos_angle.pos) {
-                        continue;
-                    }
-                    if !can_use_drill && !self.map.is_free(next_pos_angle.pos) {
-                        continue;
-                    }
-                    if can_use_fast_wheel && action.is_move() {
-                        let next_next_pos_angle = next_pos_angle.apply_action(*action);
-                        if !self.map.is_in_range(next_next_pos_angle.pos) {
-                            next_pos_angle
-                        } else if self.map.is_free(next_next_pos_angle.pos) || can_use_drill {
-                            next_next_pos_angle
-                        } else {
-                            next_pos_angle
-                        }
-                    } else {
-                        next_pos_angle
-                    }
+                let next_move = match self.next_move(
+                    current_step.pos_angle,
+                    *action,
+                    can_use_drill,
+                    can_use_fast_wheel,
+                ) {
+                    Some(next_move) => next_move,
+                    None => continue,
                 };
+                let next_pos_angle = next_move.pos_angle;
+                let next_len = current_step.len + 1;
+                let next_mode = (bot.fast_wheel_timer > next_len, bot.drill_timer > next_len);
+                debug_assert_eq!(
+                    gain_map.gain(next_pos_angle),
+                    visibility.marks(next_pos_angle, &self.map).count(),
+                    "stale gain at {:?}",
+                    next_pos_angle
+                );
                 let next = Rc::new(MoveStep {
                     pos_angle: next_pos_angle,
                     len: current_step.len + 1,
-                    mark_number: Bot::number_of_possible_mark_with_this_pos(
-                        next_pos_angle,
-                        &bot.manipulators,
-                        &self.map,
-                    ),
-                    adj_empty_number: Bot::number_of_ajd_empty_cells_of_manipulators_with_this_pos(
-                        next_pos_angle,
-                        &bot.manipulators,
-                        &self.map,
-                    ),
+                    // Cells painted on the way with fast wheels count as well. Both poses may
+                    // paint the same cells, so that is at least the larger of the two gains.
+                    mark_number: match next_move.via {
+                        None => gain_map.gain(next_pos_angle),
+                        Some(via) => gain_map.gain(via).max(gain_map.gain(next_pos_angle)),
+                    },
+                    adj_empty_number: visibility.adjacent_empty_cells(next_pos_angle, &self.map),
                     prev_action: Some(*action),
                     prev: Some(current_step.clone()),
                 });
@@ -846,8 +1523,8 @@ impl System {
                         }
                     }
                 }
-                if best.is_none() && !visited.contains(&next_pos_angle) {
-                    visited.insert(next_pos_angle);
+                if best.is_none() && !visited.contains(next_pos_angle, next_mode.0, next_mode.1) {
+                    visited.insert(next_pos_angle, next_mode.0, next_mode.1);
                     q.push_back(next);
                 }
             }
@@ -869,14 +1546,24 @@ impl System {
             .join("#")
     }
 
+    pub fn is_solved(&self) -> bool {
+        self.map.empty_cell_count == 0
+    }
+
+    // Turns taken so far, which is the score once solved.
+    pub fn turns(&self) -> usize {
+        self.bots.iter().map(|bot| bot.time()).max().unwrap()
+    }
+
     pub fn solution(&self) -> Solution {
         let id = self.map.id;
-        let score = self.bots.iter().map(|bot| bot.record.len()).max().unwrap();
+        let score = self.turns();
         Solution {
             id,
             score,
             solution: self.dump_record(),
             filename: format!("prob-{:03}-score-{:08}-ai-drill.sol", id, score),
+            trace: self.trace.as_ref().map(|trace| trace.to_json_lines()),
         }
     }
 }
@@ -887,6 +1574,9 @@ pub struct Solution {
     pub score: usize,
     pub solution: String,
     pub filename: String,
+    // JSON lines of the trace, if the run recorded one.
+    #[serde(skip)]
+    pub trace: Option<String>,
 }
 
 #[cfg(test)]
@@ -901,14 +1591,359 @@ mod test {
         let mut system = System::new(2)?;
         system.solve()?;
 
-        let mut system = System::new(21)?;
-        system.solve()?;
-        assert_eq!(system.solution().score, 1313);
-        assert_eq!(system.solution().solution, "DDDDDDDDDWWWWDDWWWWDDDDDDWWWWDDDDDDDDDWDDB(1,2)WWWWWAB(1,-2)AWWQWWWWWWWWWAWWWWWWSSAQAAAAAWAEWSSAAAQDDDDDSQDWDDDDSSSSAASSSSSSSSSSSSAAEAASQDDWDDWWWAAEWAAAAAAAAAAAAAAAWDDWEWSSSEWDDDWWDWAWWQWWWASQAASQDDDDDDDDDDDAAAESAAASSSSSDDDDWEWASSSSQSSSQSWWQWWWAAAAASSSSESSWWWWWWDDDDDDDDDDWWEWWWWDDDDDDEDEDSSSSSDDDDDDDDDDDDDDDDDDAAAWAWQWWWWWWAAQAAAASSQSSDWWAWASAAADDDSSSSSSSSSSSSSSSSSSSSSSDDFSSSSSAEWWAAWDWDDDDDDDWASSAAWWWDDAAAAAAWADWDDDDWEAAWWWWWDDDDDEDDDAAAAAAAAWWAAAAAAAQAAAASQSSSQDSSSSSSSSSSSSAFSSSAEAASWWADFDWDWWAAAWEDDWAAAAWDDWSAAASZZZZZZZZZZZZZZZZZZZZZZZZZZZZZZZZZZZZZZZZZZZZZZZZZZZZZZZZZZWSQWDDSDSSAAASAAAAAAAAAAADDWDWWEWWWAQAWWWWEWWAWWWWWWWWWWWWWWWWAWWWWWQQSDDDDDDDWDDDQDDDDDDDDDDDDDDSDDDDDDDDDDDDDDDDDDWDDSSESWWWAAEAWWWSSAASSSQSSAWWWAWEAWWWSSAAAASSAAASQSDDSQDWDAAAASEASSWWAEWWWAAAAAAWWEWSSSSQSSWWWWAAAASAAASASQSAAAAAASEAWDDDDDDDWWAAWAWDDSAAAAAAAASAAAASSSASSSSSSSSSSSSDSSDSSASSDQDSDDQDAAASSSDDSSDDDDAAAASAAADDDSSSSDDDDDDSSSSESSSSSWWWEWDDDDAAAWAAAAADSSAASQSAAWWAASSWWDWWWSSSAAAWWWSSSDDDDDDDDWWDDDDWWWWDDDDWWWWDDDDDDDDDDDDSSSSDDAASSSSSSSSSAAAAAAAAAEWAAASQDWDDDDDDDDDDDWWWWWWWWWWWWWWWWDQDWWWSSSAAWWWWWWWWWWWWDWWWAAAWWWSSSSSSSSSSDDDDDDDDDWWWWWWWWWDDWWSSSDDDDDDDSASSSSSSSSSAAAAAASSSSSSSSSSSSSSSSSSSSSDDDDDDDDDSASSSWWWAAAASSSWWWAAAAAAESAASAAADDDWWWWWWWWWWWWWWWWWWWWWWWWAAAAAAAASSSSSSSSAAAAAAAAASSSSAAAAAAAAAAAWWWWWAAWWWWAWWWWWWWWWWWWWWWWW");
+        Ok(())
+    }
 
-        // let mut system = System::new(221)?;
-        // system.solve()?;
-        // assert_eq!(system.solution().score, 1094);
+    #[test]
+    fn step_undo_test() -> Result<()> {
+        use Action::*;
+        let mut system = System::new(1)?;
+        for kind in &[
+            BoosterKind::FastWheels,
+            BoosterKind::Drill,
+            BoosterKind::Cloning,
+            BoosterKind::ExtendManipulator,
+        ] {
+            system.inventory.add(*kind, 0);
+        }
+        let snapshot = system.clone();
+
+        let mut deltas = vec![];
+        for action in &[
+            AttachFastWheels,
+            MoveRight,
+            AttachDrill,
+            // Two cells each, the last two drilled, up to the east border of the map.
+            MoveRight,
+            MoveRight,
+            MoveRight,
+            Cloning,
+            ExtendManipulator(PosDiff::new(1, 2)),
+            TurnClockWise,
+        ] {
+            deltas.push(system.step(0, *action));
+        }
+        assert!(deltas.iter().any(|delta| !delta.drilled.is_empty()));
+        assert_eq!(system.bots.len(), 2);
+        assert!(system.map.empty_cell_count < snapshot.map.empty_cell_count);
+
+        while let Some(delta) = deltas.pop() {
+            system.undo(delta);
+        }
+        assert_eq!(system.map, snapshot.map);
+        assert_eq!(system.bots, snapshot.bots);
+        assert_eq!(system.inventory, snapshot.inventory);
+        Ok(())
+    }
+
+    #[test]
+    fn fast_wheels_test() -> Result<()> {
+        use Action::*;
+        // A wall on (4,0), below a free row.
+        let task = Task::parse(
+            0,
+            "(0,0),(14,0),(14,2),(0,2)#(0,0)#(4,0),(5,0),(5,1),(4,1)#",
+        )?;
+        let mut system = System::from_task(task);
+        system.inventory.add(BoosterKind::FastWheels, 0);
+        system.inventory.add(BoosterKind::Drill, 0);
+        system.step(0, AttachFastWheels);
+        let mut drilling = system.clone();
+
+        // The second cell is a wall, so the bot moves one cell only.
+        system.step(0, MoveRight);
+        let delta = system.step(0, MoveRight);
+        assert_eq!(system.bots[0].pos_angle.pos, Pos::new(3, 0));
+        assert!(delta.drilled.is_empty());
+
+        // A drill goes through it.
+        drilling.step(0, AttachDrill);
+        drilling.step(0, MoveRight);
+        let delta = drilling.step(0, MoveRight);
+        assert_eq!(drilling.bots[0].pos_angle.pos, Pos::new(4, 0));
+        assert_eq!(delta.drilled, vec![Pos::new(4, 0)]);
+        Ok(())
+    }
+
+    #[test]
+    fn fast_wheels_wait_test() -> Result<()> {
+        // On these maps fast wheels leave a bot no move which paints for a while.
+        for &(seed, boosters) in &[(33, 2), (40, 3)] {
+            let options = crate::generate::GenerateOptions {
+                boosters,
+                seed,
+                ..crate::generate::GenerateOptions::default()
+            };
+            let mut system = System::from_task(crate::generate::generate(0, &options)?);
+            system.solve()?;
+            assert_eq!(system.map.empty_cell_count, 0);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn should_attach_fast_wheels_test() -> Result<()> {
+        use Action::*;
+        let task = Task::parse(
+            0,
+            "(0,0),(14,0),(14,2),(0,2)#(0,1)#(4,0),(5,0),(5,1),(4,1)#",
+        )?;
+        let mut system = System::from_task(task);
+        // Not without fast wheels in the inventory.
+        assert!(!system.should_attach_fast_wheels(&system.bots[0], MoveRight));
+
+        system.inventory.add(BoosterKind::FastWheels, 0);
+        // Only on a long straight run.
+        assert!(system.should_attach_fast_wheels(&system.bots[0], MoveRight));
+        assert!(!system.should_attach_fast_wheels(&system.bots[0], MoveDown));
+        assert!(!system.should_attach_fast_wheels(&system.bots[0], TurnClockWise));
+        system.step(0, MoveDown);
+        assert!(!system.should_attach_fast_wheels(&system.bots[0], MoveRight));
+
+        // Not twice.
+        system.step(0, MoveUp);
+        system.inventory.add(BoosterKind::FastWheels, 0);
+        system.step(0, AttachFastWheels);
+        assert!(!system.should_attach_fast_wheels(&system.bots[0], MoveRight));
+        Ok(())
+    }
+
+    #[test]
+    fn try_step_test() -> Result<()> {
+        use Action::*;
+        let task = Task::parse(0, "(0,0),(3,0),(3,3),(0,3)#(0,0)##X(2,2);B(0,1)")?;
+        let mut system = System::from_task(task);
+        assert!(system.try_step(0, MoveDown).is_err());
+        assert!(system.try_step(1, DoNothing).is_err());
+        assert!(system
+            .try_step(0, ExtendManipulator(PosDiff::new(3, 0)))
+            .is_err());
+        // The booster is not picked up yet.
+        assert!(system
+            .try_step(0, ExtendManipulator(PosDiff::new(2, 0)))
+            .is_err());
+        assert!(system.try_step(0, Cloning).is_err());
+        assert!(system.bots()[0].record().is_empty());
+
+        system.try_step(0, MoveUp)?;
+        system.try_step(0, ExtendManipulator(PosDiff::new(2, 0)))?;
+        assert_eq!(system.bots()[0].pos_angle().pos, Pos::new(0, 1));
+        assert_eq!(system.bots()[0].manipulators().len(), 5);
+        assert_eq!(
+            system.booster_at(Pos::new(2, 2)),
+            Some(BoosterKind::Mysterious)
+        );
+        assert!(Task::parse(0, "(0,0),(3,0),(3,3),(0,3)#(0,0)").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn pick_up_test() -> Result<()> {
+        use Action::*;
+        let task = Task::parse(0, "(0,0),(6,0),(6,2),(0,2)#(0,0)##B(2,0);F(3,1);X(5,1)")?;
+        let mut system = System::from_task(task);
+        system.bots.push(Bot::new(Pos::new(5, 0)));
+        let fetch = Plan::new(vec![Goal::PickUp(Pos::new(3, 1))]);
+        system.bots[1].plan = fetch.clone();
+        system.reservations.reserve(
+            Pos::new(3, 1),
+            Reservation {
+                bot: 1,
+                kind: BoosterKind::FastWheels,
+                eta: 3,
+            },
+        );
+        assert!(!system.has_booster(BoosterKind::FastWheels));
+
+        // Nobody went for the first one, and bot 1 loses the second one to bot 0. A booster
+        // picked up in a turn can be used by any bot from the next turn.
+        system.step(0, MoveRight);
+        let first = system.step(0, MoveRight);
+        assert_eq!(
+            first.picked,
+            vec![(Pos::new(2, 0), BoosterKind::ExtendManipulator)]
+        );
+        assert_eq!(system.booster_at(Pos::new(2, 0)), None);
+        let extend = ExtendManipulator(PosDiff::new(2, 0));
+        assert!(system.check_action(1, extend).is_err());
+        assert!(system.check_action(0, extend).is_ok());
+        system.step(0, MoveUp);
+        let second = system.step(0, MoveRight);
+        assert_eq!(
+            second.picked,
+            vec![(Pos::new(3, 1), BoosterKind::FastWheels)]
+        );
+        assert!(system.bots[1].plan.is_empty());
+        assert_eq!(system.reservations.iter().count(), 0);
+        assert_eq!(system.inventory.count(BoosterKind::FastWheels, 4), 1);
+
+        system.undo(second);
+        assert_eq!(system.inventory.count(BoosterKind::FastWheels, 4), 0);
+        assert_eq!(system.bots[1].plan, fetch);
+        assert!(system.reservations.is_reserved(Pos::new(3, 1)));
+        assert_eq!(
+            system.booster_at(Pos::new(3, 1)),
+            Some(BoosterKind::FastWheels)
+        );
+
+        // Bot 0 is closer now, so it takes over the plan.
+        for _ in 0..3 {
+            system.step(1, DoNothing);
+        }
+        system.bots[0].plan = Plan::default();
+        system.reassign_boosters();
+        assert!(system.bots[1].plan.is_empty());
+        assert_eq!(system.bots[0].plan, fetch);
+        let second = system.step(0, MoveRight);
+        assert!(second.dropped.is_empty());
+        assert!(system.bots[0].plan.is_empty());
+        system.try_step(0, AttachFastWheels)?;
+        assert_eq!(system.inventory.count(BoosterKind::FastWheels, 4), 0);
+        Ok(())
+    }
+
+    #[test]
+    fn follow_plan_test() -> Result<()> {
+        let task = Task::parse(0, "(0,0),(6,0),(6,2),(0,2)#(0,0)##X(4,1)")?;
+        let mut system = System::from_task(task);
+        system.bots[0].plan = Plan::new(vec![Goal::Activate(BoosterKind::Cloning)]);
+
+        // Without the booster the plan is given up.
+        let decision = system.decide(0)?;
+        assert_eq!(decision.plan, Some(Plan::default()));
+
+        // Cloning needs a mysterious point, so the bot goes to one first.
+        system.inventory.add(BoosterKind::Cloning, 0);
+        let decision = system.decide(0)?;
+        assert_eq!(
+            decision.plan.unwrap().goals(),
+            &[
+                Goal::GoTo(Pos::new(4, 1)),
+                Goal::Activate(BoosterKind::Cloning)
+            ]
+        );
+
+        // On the mysterious point, a booster picked up in this turn is waited for.
+        let mut system =
+            System::from_task(Task::parse(0, "(0,0),(6,0),(6,2),(0,2)#(4,1)##X(4,1)")?);
+        system.bots[0].plan = Plan::new(vec![Goal::Activate(BoosterKind::Cloning)]);
+        system.inventory.add(BoosterKind::Cloning, 1);
+        let decision = system.decide(0)?;
+        assert_eq!(decision.plan, None);
+        assert_ne!(decision.action, Action::Cloning);
+        system.step(0, decision.action);
+        assert_eq!(system.decide(0)?.action, Action::Cloning);
+
+        // A goal already reached is skipped.
+        let pos = system.bots[0].pos_angle.pos;
+        system.bots[0].plan =
+            Plan::new(vec![Goal::GoTo(pos), Goal::Activate(BoosterKind::Cloning)]);
+        let decision = system.decide(0)?;
+        assert_eq!(decision.action, Action::Cloning);
+        assert_eq!(
+            decision.plan.unwrap().goals(),
+            &[Goal::Activate(BoosterKind::Cloning)]
+        );
+
+        // The simulator has no beacons, so the bot plans afresh and clones.
+        system.bots[0].plan = Plan::new(vec![Goal::Activate(BoosterKind::Teleport)]);
+        system.inventory.add(BoosterKind::Teleport, 0);
+        let decision = system.decide(0)?;
+        assert_eq!(decision.action, Action::Cloning);
+        assert_eq!(
+            decision.plan.unwrap().goals(),
+            &[Goal::Activate(BoosterKind::Cloning)]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn place_beacon_test() -> Result<()> {
+        use Action::*;
+        let task = Task::parse(0, "(0,0),(6,0),(6,2),(0,2)#(0,0)##X(4,1)")?;
+        let mut system = System::from_task(task);
+        let beacon = Pos::new(3, 0);
+        system.bots[0].plan = Plan::new(vec![Goal::PlaceBeacon(beacon)]);
+
+        // Without the booster the plan is given up.
+        let decision = system.decide(0)?;
+        assert_eq!(decision.plan, Some(Plan::default()));
+
+        // The bot goes to the cell, and installs the beacon there.
+        system.inventory.add(BoosterKind::Teleport, 0);
+        let mut deltas = vec![];
+        while !system.bots[0].plan.is_empty() {
+            assert!(deltas.len() < 10);
+            let decision = system.decide(0)?;
+            if let Some(plan) = decision.plan {
+                system.bots[0].plan = plan;
+            }
+            deltas.push(system.try_step(0, decision.action)?);
+        }
+        assert_eq!(deltas.last().unwrap().action, InstallBeacon);
+        assert_eq!(system.bots[0].pos_angle.pos, beacon);
+        assert_eq!(system.beacons, vec![beacon]);
+
+        // Not twice on the same cell, nor on a mysterious point.
+        system.inventory.add(BoosterKind::Teleport, 0);
+        assert!(system.check_action(0, InstallBeacon).is_err());
+        system.bots[0].plan = Plan::new(vec![Goal::PlaceBeacon(beacon)]);
+        assert_eq!(system.decide(0)?.plan, Some(Plan::default()));
+        system.bots[0].plan = Plan::new(vec![Goal::PlaceBeacon(Pos::new(4, 1))]);
+        assert_eq!(system.decide(0)?.plan, Some(Plan::default()));
+
+        // The bot teleports to beacons only.
+        deltas.push(system.try_step(0, MoveRight)?);
+        assert!(system.check_action(0, Shift(Pos::new(0, 0))).is_err());
+        deltas.push(system.try_step(0, Shift(beacon))?);
+        assert_eq!(system.bots[0].pos_angle.pos, beacon);
+
+        while let Some(delta) = deltas.pop() {
+            system.undo(delta);
+        }
+        assert!(system.beacons.is_empty());
+        assert_eq!(system.bots[0].pos_angle.pos, Pos::new(0, 0));
+        assert_eq!(system.inventory.count(BoosterKind::Teleport, 0), 2);
+
+        // A booster picked up in this turn is waited for.
+        let mut system = System::from_task(Task::parse(0, "(0,0),(6,0),(6,2),(0,2)#(0,0)##")?);
+        system.bots[0].plan = Plan::new(vec![Goal::PlaceBeacon(Pos::new(0, 0))]);
+        system.inventory.add(BoosterKind::Teleport, 1);
+        let decision = system.decide(0)?;
+        assert_eq!(decision.plan, None);
+        assert_ne!(decision.action, InstallBeacon);
+        system.step(0, decision.action);
+        assert_eq!(system.decide(0)?.action, InstallBeacon);
+        Ok(())
+    }
+
+    #[test]
+    fn sweep_test() -> Result<()> {
+        let task = Task::parse(0, "(0,0),(8,0),(8,3),(0,3)#(0,0)##")?;
+        let mut system = System::from_task(task);
+        let region = Region::new(Pos::new(5, 0), Pos::new(8, 3));
+        system.bots[0].plan = Plan::new(vec![Goal::Sweep(region)]);
+
+        // Painting anywhere may change where the sweep goes.
+        assert_eq!(system.decide(0)?.paint_radius, Some(i32::MAX));
+
+        // The bot paints the region, and then plans afresh.
+        let mut turns = 0;
+        while !system.bots[0].plan.is_empty() {
+            assert!(turns < 20);
+            let decision = system.decide(0)?;
+            if let Some(plan) = decision.plan {
+                system.bots[0].plan = plan;
+            }
+            system.try_step(0, decision.action)?;
+            turns += 1;
+        }
+        for y in 0..3 {
+            for x in 5..8 {
+                assert!(!system.map.is_empty(Pos::new(x, y)));
+            }
+        }
+        assert!(system.map.empty_cell_count > 0);
         Ok(())
     }
 
@@ -1070,4 +2105,47 @@ mod test {
             ]
         );
     }
+
+    #[test]
+    fn parallel_solve_test() -> Result<()> {
+        // Deciding in parallel gives the same solution as one bot after another.
+        use crate::generate::{generate, GenerateOptions};
+        let pools = [1, 4]
+            .iter()
+            .map(|&threads| rayon::ThreadPoolBuilder::new().num_threads(threads).build())
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+        let solve = |options: &GenerateOptions, pool: &rayon::ThreadPool| -> Result<String> {
+            let mut system = System::from_task(generate(0, options)?);
+            pool.install(|| system.solve())?;
+            Ok(system.dump_record())
+        };
+        // Small maps with many boosters for each other, and maps which once came out different.
+        let small = (1..=5).flat_map(|boosters| {
+            (0..12).map(move |seed| GenerateOptions {
+                size: 16,
+                obstacles: 2,
+                boosters,
+                seed,
+                ..GenerateOptions::default()
+            })
+        });
+        let found = [(14, 5), (37, 5)]
+            .iter()
+            .map(|&(seed, boosters)| GenerateOptions {
+                boosters,
+                seed,
+                ..GenerateOptions::default()
+            });
+        for options in small.chain(found) {
+            assert_eq!(
+                solve(&options, &pools[1])?,
+                solve(&options, &pools[0])?,
+                "seed: {}, boosters: {}, size: {}",
+                options.seed,
+                options.boosters,
+                options.size
+            );
+        }
+        Ok(())
+    }
 }
@@ -1,3 +1,4 @@
+use crate::grid::*;
 use crate::prelude::*;
 
 #[derive(Hash, Copy, Clone, PartialEq, Eq, Debug, PartialOrd, Ord)]
@@ -43,6 +44,15 @@ impl Angle {
             A270 => A0,
         }
     }
+    pub fn inverse(self) -> Angle {
+        use Angle::*;
+        match self {
+            A0 => A0,
+            A90 => A270,
+            A180 => A180,
+            A270 => A90,
+        }
+    }
     pub fn turn_counter_clock_wise(self) -> Angle {
         use Angle::*;
         match self {
@@ -102,22 +112,16 @@ pub enum BoosterKind {
 }
 
 impl BoosterKind {
-    fn from_char(c: char) -> BoosterKind {
+    fn from_char(c: char) -> Result<BoosterKind> {
         use BoosterKind::*;
-        if c == 'B' {
-            ExtendManipulator
-        } else if c == 'F' {
-            FastWheels
-        } else if c == 'L' {
-            Drill
-        } else if c == 'X' {
-            Mysterious
-        } else if c == 'R' {
-            Teleport
-        } else if c == 'C' {
-            Cloning
-        } else {
-            unreachable!()
+        match c {
+            'B' => Ok(ExtendManipulator),
+            'F' => Ok(FastWheels),
+            'L' => Ok(Drill),
+            'X' => Ok(Mysterious),
+            'R' => Ok(Teleport),
+            'C' => Ok(Cloning),
+            c => Err(failure::format_err!("unknown booster: {}", c)),
         }
     }
 }
@@ -125,7 +129,7 @@ impl BoosterKind {
 impl std::fmt::Display for BoosterKind {
     fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
         use BoosterKind::*;
-        writeln!(
+        write!(
             f,
             "{}",
             match self {
@@ -140,105 +144,311 @@ impl std::fmt::Display for BoosterKind {
     }
 }
 
+// A task whose tours are normalized and which lies on the grid of its map. Build one with
+// Task::new or Task::parse.
 pub struct Task {
-    pub id: TaskId,
-    pub map: Tour,
-    pub bot: Pos,
-    pub obstacles: Vec<Tour>,
-    pub boosters: Vec<Booster>,
+    id: TaskId,
+    map: Tour,
+    bot: Pos,
+    obstacles: Vec<Tour>,
+    boosters: Vec<Booster>,
 }
 
 impl Task {
+    pub fn new(
+        id: TaskId,
+        map: Tour,
+        bot: Pos,
+        obstacles: Vec<Tour>,
+        boosters: Vec<Booster>,
+    ) -> Result<Task> {
+        let mut task = Task {
+            id,
+            map,
+            bot,
+            obstacles,
+            boosters,
+        };
+        task.normalize()?;
+        Ok(task)
+    }
+
+    pub fn id(&self) -> TaskId {
+        self.id
+    }
+
+    // The outline of the map, counter-clockwise.
+    pub fn map(&self) -> &[Pos] {
+        &self.map
+    }
+
+    pub fn bot(&self) -> Pos {
+        self.bot
+    }
+
+    // The outlines of the obstacles, counter-clockwise.
+    pub fn obstacles(&self) -> &[Tour] {
+        &self.obstacles
+    }
+
+    pub fn boosters(&self) -> &[Booster] {
+        &self.boosters
+    }
+
     pub fn read_with_id(id: TaskId) -> Result<Task> {
         let mut path = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
         path.push(format!("contest/problem/prob-{:03}.desc", id));
         Task::read_from(id, path)
     }
 
-    pub fn parse_tour(s: &str) -> Tour {
+    // Ids of the tasks in contest/problem, in order.
+    pub fn ids() -> Result<Vec<TaskId>> {
+        let dir = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("contest/problem");
+        let mut ids = std::fs::read_dir(dir)?
+            .filter_map(|entry| {
+                Task::id_from_file_name(&entry.ok()?.file_name().into_string().ok()?)
+            })
+            .collect::<Vec<_>>();
+        ids.sort();
+        Ok(ids)
+    }
+
+    // The id of prob-NNN.desc.
+    pub fn id_from_file_name(name: &str) -> Option<TaskId> {
+        name.strip_prefix("prob-")?
+            .strip_suffix(".desc")?
+            .parse()
+            .ok()
+    }
+
+    pub fn parse_tour(s: &str) -> Result<Tour> {
         if s.is_empty() {
-            vec![]
-        } else {
-            s[1..s.len() - 1]
-                .split("),(")
-                .map(|p| {
-                    let mut x_y = p.split(',');
-                    Pos::new(
-                        x_y.next().unwrap().parse().unwrap(),
-                        x_y.next().unwrap().parse().unwrap(),
-                    )
-                })
-                .collect()
-        }
-    }
-
-    fn parse_pos(s: &str) -> Pos {
-        assert!(!s.is_empty());
-        let mut x_y = s[1..s.len() - 1].split(',');
-        Pos::new(
-            x_y.next().unwrap().parse().unwrap(),
-            x_y.next().unwrap().parse().unwrap(),
-        )
+            return Ok(vec![]);
+        }
+        s.strip_prefix('(')
+            .and_then(|s| s.strip_suffix(')'))
+            .ok_or_else(|| failure::format_err!("bad tour: {}", s))?
+            .split("),(")
+            .map(Task::parse_x_y)
+            .collect()
+    }
+
+    // "x,y"
+    fn parse_x_y(s: &str) -> Result<Pos> {
+        let mut x_y = s.split(',');
+        match (x_y.next(), x_y.next(), x_y.next()) {
+            (Some(x), Some(y), None) => Ok(Pos::new(x.parse()?, y.parse()?)),
+            _ => Err(failure::format_err!("bad position: {}", s)),
+        }
+    }
+
+    fn parse_pos(s: &str) -> Result<Pos> {
+        s.strip_prefix('(')
+            .and_then(|s| s.strip_suffix(')'))
+            .ok_or_else(|| failure::format_err!("bad position: {}", s))
+            .and_then(Task::parse_x_y)
     }
 
-    fn parse_obstacles(s: &str) -> Vec<Tour> {
+    fn parse_obstacles(s: &str) -> Result<Vec<Tour>> {
         if s.is_empty() {
-            vec![]
+            Ok(vec![])
         } else {
             s.split(';').map(Task::parse_tour).collect()
         }
     }
 
-    fn parse_boosters(s: &str) -> Vec<Booster> {
+    pub fn parse_boosters(s: &str) -> Result<Vec<Booster>> {
         if s.is_empty() {
-            vec![]
+            Ok(vec![])
         } else {
             s.split(';').map(Task::parse_booster).collect()
         }
     }
 
-    fn parse_booster(s: &str) -> Booster {
-        assert!(!s.is_empty());
+    fn parse_booster(s: &str) -> Result<Booster> {
         // F(5,6)
-        let pos = Task::parse_pos(&s[1..]);
-        Booster {
-            pos,
-            kind: BoosterKind::from_char(s.chars().next().unwrap()),
-        }
+        let mut chars = s.chars();
+        let kind = chars
+            .next()
+            .ok_or_else(|| failure::err_msg("empty booster"))
+            .and_then(BoosterKind::from_char)?;
+        Ok(Booster {
+            pos: Task::parse_pos(chars.as_str())?,
+            kind,
+        })
     }
 
     pub fn read_from(id: TaskId, path: impl AsRef<Path>) -> Result<Task> {
         let path = path.as_ref();
         debug!("read: {}", path.display());
         let s = std::fs::read_to_string(path)?;
-        let s = s.split('#').collect::<Vec<_>>();
-        assert_eq!(s.len(), 4);
+        Task::parse(id, &s)
+    }
 
-        let map = Task::parse_tour(&s[0]);
-        let bot = Task::parse_pos(&s[1]);
-        let obstacles = Task::parse_obstacles(&s[2]);
-        let boosters = Task::parse_boosters(&s[3]);
+    pub fn parse(id: TaskId, s: &str) -> Result<Task> {
+        let s = s.trim().split('#').collect::<Vec<_>>();
+        if s.len() != 4 {
+            return Err(failure::format_err!(
+                "a task has 4 parts separated by '#', not {}",
+                s.len()
+            ));
+        }
 
-        Ok(Task {
-            id,
-            map,
-            bot,
-            obstacles,
-            boosters,
-        })
+        let map = Task::parse_tour(s[0]).context("map")?;
+        let bot = Task::parse_pos(s[1]).context("bot")?;
+        let obstacles = Task::parse_obstacles(s[2]).context("obstacles")?;
+        let boosters = Task::parse_boosters(s[3]).context("boosters")?;
+
+        Task::new(id, map, bot, obstacles, boosters)
+    }
+
+    // Normalizes the map and the obstacles with normalize_tour, and checks that everything is on
+    // the grid of the map.
+    fn normalize(&mut self) -> Result<()> {
+        self.map = Task::normalize_tour(&self.map).context("map")?;
+        if self.map.iter().any(|pos| pos.x < 0 || pos.y < 0) {
+            return Err(failure::err_msg("map: negative coordinates"));
+        }
+        let (max_x, max_y) = (self.max_x(), self.max_y());
+        for (i, obstacle) in self.obstacles.iter_mut().enumerate() {
+            *obstacle =
+                Task::normalize_tour(obstacle).with_context(|_| format!("obstacle {}", i))?;
+            if obstacle
+                .iter()
+                .any(|pos| pos.x < 0 || pos.y < 0 || pos.x > max_x || pos.y > max_y)
+            {
+                return Err(failure::format_err!("obstacle {}: off the map", i));
+            }
+        }
+        let on_grid = |pos: Pos| pos.x >= 0 && pos.y >= 0 && pos.x < max_x && pos.y < max_y;
+        if !on_grid(self.bot) {
+            return Err(failure::format_err!("bot: off the map: {:?}", self.bot));
+        }
+        if let Some(booster) = self.boosters.iter().find(|booster| !on_grid(booster.pos)) {
+            return Err(failure::format_err!("booster off the map: {:?}", booster));
+        }
+        Ok(())
+    }
+
+    // Drops repeated vertices and vertices in the middle of a straight edge, and makes the tour
+    // counter-clockwise. Fails on diagonal edges, on tours which double back or cross themselves,
+    // and on tours with no area.
+    pub fn normalize_tour(tour: &[Pos]) -> Result<Tour> {
+        let mut tour = tour.to_vec();
+        tour.dedup();
+        while tour.len() > 1 && tour.first() == tour.last() {
+            tour.pop();
+        }
+        for i in 0..tour.len() {
+            let (a, b) = (tour[i], tour[(i + 1) % tour.len()]);
+            if a.x != b.x && a.y != b.y {
+                return Err(failure::format_err!("diagonal edge: {:?} -> {:?}", a, b));
+            }
+        }
+
+        // A removal can make the vertices around it collinear, so repeat until nothing changes.
+        loop {
+            let len = tour.len();
+            let mut i = 0;
+            while tour.len() >= 3 && i < tour.len() {
+                let n = tour.len();
+                let (prev, cur, next) = (tour[(i + n - 1) % n], tour[i], tour[(i + 1) % n]);
+                if (prev.x == cur.x && cur.x == next.x) || (prev.y == cur.y && cur.y == next.y) {
+                    let between = (prev.x - cur.x) * (next.x - cur.x) <= 0
+                        && (prev.y - cur.y) * (next.y - cur.y) <= 0;
+                    if !between {
+                        return Err(failure::format_err!("the tour doubles back at {:?}", cur));
+                    }
+                    tour.remove(i);
+                } else {
+                    i += 1;
+                }
+            }
+            if tour.len() == len {
+                break;
+            }
+        }
+        if tour.len() < 4 {
+            return Err(failure::err_msg("the tour has no area"));
+        }
+
+        // Edges are axis-aligned, so two of them meet if and only if their bounding boxes do.
+        let n = tour.len();
+        let edge = |i: usize| {
+            let (a, b) = (tour[i], tour[(i + 1) % n]);
+            (a.x.min(b.x), a.x.max(b.x), a.y.min(b.y), a.y.max(b.y))
+        };
+        for i in 0..n {
+            for j in i + 2..n {
+                if i == 0 && j == n - 1 {
+                    continue;
+                }
+                let (e, f) = (edge(i), edge(j));
+                if e.0 <= f.1 && f.0 <= e.1 && e.2 <= f.3 && f.2 <= e.3 {
+                    return Err(failure::format_err!(
+                        "the tour crosses itself: {:?} -> {:?} and {:?} -> {:?}",
+                        tour[i],
+                        tour[(i + 1) % n],
+                        tour[j],
+                        tour[(j + 1) % n]
+                    ));
+                }
+            }
+        }
+
+        let twice_area: i64 = (0..n)
+            .map(|i| {
+                let (a, b) = (tour[i], tour[(i + 1) % n]);
+                i64::from(a.x) * i64::from(b.y) - i64::from(b.x) * i64::from(a.y)
+            })
+            .sum();
+        if twice_area < 0 {
+            tour.reverse();
+        }
+        Ok(tour)
+    }
+
+    fn format_tour(tour: &[Pos]) -> String {
+        tour.iter()
+            .map(|pos| format!("({},{})", pos.x, pos.y))
+            .collect::<Vec<_>>()
+            .join(",")
     }
 
-    fn max_x(&self) -> i32 {
+    pub(crate) fn max_x(&self) -> i32 {
         assert!(!self.map.is_empty());
         self.map.iter().map(|pos| pos.x).max().unwrap()
     }
 
-    fn max_y(&self) -> i32 {
+    pub(crate) fn max_y(&self) -> i32 {
         assert!(!self.map.is_empty());
         self.map.iter().map(|pos| pos.y).max().unwrap()
     }
 }
 
+// The .desc format.
+impl std::fmt::Display for Task {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(
+            f,
+            "{}#({},{})#{}#{}",
+            Task::format_tour(&self.map),
+            self.bot.x,
+            self.bot.y,
+            self.obstacles
+                .iter()
+                .map(|obstacle| Task::format_tour(obstacle))
+                .collect::<Vec<_>>()
+                .join(";"),
+            self.boosters
+                .iter()
+                .map(|booster| format!("{}({},{})", booster.kind, booster.pos.x, booster.pos.y))
+                .collect::<Vec<_>>()
+                .join(";")
+        )
+    }
+}
+
 #[derive(Hash, Copy, Clone, PartialEq, Eq, Debug, PartialOrd, Ord)]
 pub enum Cell {
     Wall,
@@ -246,11 +456,13 @@ pub enum Cell {
     Marked,
 }
 
+#[derive(Debug, Clone, PartialEq)]
 pub struct Map {
     pub id: u64,
     pub max_x: i32,
     pub max_y: i32,
-    pub cells: Vec<Vec<Cell>>,
+    pub walls: BitGrid,
+    pub painted: BitGrid,
     pub empty_cell_count: usize,
     pub bot_start_pos: Pos,
     pub boosters: Vec<Booster>,
@@ -259,40 +471,45 @@ pub struct Map {
 impl Map {
     #[cfg(test)]
     pub fn dump_map(&self) -> String {
-        let mut rectangle = (0..self.max_x)
-            .map(|x| {
-                (0..self.max_y)
-                    .map(|y| match self.cells[x as usize][y as usize] {
-                        Cell::Wall => '#',
-                        Cell::Empty => '.',
-                        Cell::Marked => '-',
-                    })
-                    .collect::<Vec<char>>()
-            })
-            .collect::<Vec<_>>();
+        let mut rectangle = Grid::new(self.max_x, self.max_y, ' ');
+        for pos in rectangle.positions() {
+            rectangle[pos] = match self.cell(pos) {
+                Cell::Wall => '#',
+                Cell::Empty => '.',
+                Cell::Marked => '-',
+            };
+        }
 
-        rectangle[self.bot_start_pos.x as usize][self.bot_start_pos.y as usize] = 'O';
+        rectangle[self.bot_start_pos] = 'O';
 
         for b in &self.boosters {
-            rectangle[b.pos.x as usize][b.pos.y as usize] =
-                b.kind.to_string().chars().next().unwrap();
+            rectangle[b.pos] = b.kind.to_string().chars().next().unwrap();
         }
 
-        (0..self.max_y as usize)
+        (0..self.max_y)
             .rev()
             .map(|y| {
-                (0..self.max_x as usize)
-                    .map(|x| rectangle[x][y])
+                (0..self.max_x)
+                    .map(|x| rectangle[Pos::new(x, y)])
                     .collect::<String>()
             })
             .collect::<Vec<_>>()
             .join("\n")
     }
 
-    fn fill_tour(tour: &[Pos]) -> HashSet<Pos> {
+    pub(crate) fn fill_tour(tour: &[Pos], max_x: i32, max_y: i32) -> BitGrid {
+        // The interior is on the left of a counter-clockwise tour, and on the right otherwise.
+        let twice_area: i64 = (0..tour.len())
+            .map(|i| {
+                let (a, b) = (tour[i], tour[(i + 1) % tour.len()]);
+                i64::from(a.x) * i64::from(b.y) - i64::from(b.x) * i64::from(a.y)
+            })
+            .sum();
+        let clockwise = twice_area < 0;
+
         // BFS to fill interior of tour
-        let mut visited = HashSet::new();
-        let mut filled = HashSet::new();
+        let mut visited = BitGrid::new(max_x, max_y);
+        let mut filled = BitGrid::new(max_x, max_y);
         let mut q = VecDeque::new();
 
         for i in 0..tour.len() {
@@ -313,7 +530,8 @@ impl Map {
                             .map(|y| (Pos::new(x, y), Pos::new(x - 1, y)))
                             .collect()
                     } else {
-                        unreachable!();
+                        // A repeated vertex.
+                        vec![]
                     }
                 } else if start.y == end.y {
                     let y = start.y;
@@ -335,7 +553,16 @@ impl Map {
                 }
             };
             for (interior, wall) in borders {
-                visited.insert(wall);
+                let (interior, wall) = if clockwise {
+                    (wall, interior)
+                } else {
+                    (interior, wall)
+                };
+                // The outside of the outer tour lies off the grid.
+                if visited.is_in_range(wall) {
+                    visited.insert(wall);
+                }
+                assert!(visited.is_in_range(interior));
                 visited.insert(interior);
                 filled.insert(interior);
                 q.push_back(interior);
@@ -345,7 +572,7 @@ impl Map {
         while let Some(current) = q.pop_front() {
             for diff in PosDiff::gen_all_diff() {
                 let next = current + *diff;
-                if visited.contains(&next) {
+                if !visited.is_in_range(next) || visited.contains(next) {
                     continue;
                 }
                 visited.insert(next);
@@ -359,88 +586,252 @@ impl Map {
     pub fn new(task: Task) -> Map {
         let max_x = task.max_x();
         let max_y = task.max_y();
-        let mut cells = vec![vec![Cell::Wall; max_y as usize]; max_x as usize];
 
-        for cell in Map::fill_tour(&task.map) {
-            assert!(0 <= cell.x);
-            assert!(cell.x < max_x);
-            assert!(0 <= cell.y);
-            assert!(cell.y < max_y);
-            cells[cell.x as usize][cell.y as usize] = Cell::Empty;
+        let mut walls = BitGrid::new(max_x, max_y);
+        let interior = Map::fill_tour(&task.map, max_x, max_y);
+        for y in 0..max_y {
+            for x in 0..max_x {
+                let pos = Pos::new(x, y);
+                if !interior.contains(pos) {
+                    walls.insert(pos);
+                }
+            }
         }
 
         for obstacle in &task.obstacles {
-            for cell in Map::fill_tour(obstacle) {
-                assert!(0 <= cell.x);
-                assert!(cell.x < max_x);
-                assert!(0 <= cell.y);
-                assert!(cell.y < max_y);
-                cells[cell.x as usize][cell.y as usize] = Cell::Wall;
+            for cell in Map::fill_tour(obstacle, max_x, max_y).iter() {
+                walls.insert(cell);
             }
         }
 
-        let empty_cell_count = cells
-            .iter()
-            .map(|r| r.iter().filter(|cell| **cell == Cell::Empty).count())
-            .sum();
+        let empty_cell_count = (max_x * max_y) as usize - walls.count();
 
         Map {
             id: task.id,
             max_x,
             max_y,
-            cells,
+            walls,
+            painted: BitGrid::new(max_x, max_y),
             empty_cell_count,
             bot_start_pos: task.bot,
             boosters: task.boosters,
         }
     }
 
+    pub fn cell(&self, pos: Pos) -> Cell {
+        assert!(self.is_in_range(pos));
+        if self.walls.contains(pos) {
+            Cell::Wall
+        } else if self.painted.contains(pos) {
+            Cell::Marked
+        } else {
+            Cell::Empty
+        }
+    }
+
     pub fn is_in_range(&self, pos: Pos) -> bool {
         0 <= pos.x && pos.x < self.max_x && 0 <= pos.y && pos.y < self.max_y
     }
 
     pub fn is_empty(&self, pos: Pos) -> bool {
-        self.is_in_range(pos) && self.cells[pos.x as usize][pos.y as usize] == Cell::Empty
+        self.is_in_range(pos) && !self.walls.contains(pos) && !self.painted.contains(pos)
     }
 
     pub fn is_free(&self, pos: Pos) -> bool {
-        self.is_in_range(pos) && self.cells[pos.x as usize][pos.y as usize] != Cell::Wall
+        self.is_in_range(pos) && !self.walls.contains(pos)
     }
 
     pub fn is_wall(&self, pos: Pos) -> bool {
-        self.is_in_range(pos) && self.cells[pos.x as usize][pos.y as usize] == Cell::Wall
+        self.walls.contains(pos)
     }
 
     pub fn do_drill(&mut self, pos: Pos) {
         assert!(self.is_in_range(pos));
         assert!(self.is_wall(pos));
-        self.cells[pos.x as usize][pos.y as usize] = Cell::Marked;
+        self.walls.remove(pos);
+        self.painted.insert(pos);
     }
 
-    pub fn mark_pos(&mut self, pos: Pos) {
-        if !self.is_in_range(pos) {
-            return;
-        }
-        let cell: &mut Cell = &mut self.cells[pos.x as usize][pos.y as usize];
-        match cell {
-            Cell::Empty => {
-                self.empty_cell_count -= 1;
-                *cell = Cell::Marked;
-            }
-            Cell::Wall => {}
-            Cell::Marked => {}
+    // Returns true if the cell is newly painted.
+    pub fn mark_pos(&mut self, pos: Pos) -> bool {
+        if self.is_empty(pos) {
+            self.empty_cell_count -= 1;
+            self.painted.insert(pos);
+            true
+        } else {
+            false
         }
     }
+
+    pub fn unmark_pos(&mut self, pos: Pos) {
+        assert_eq!(self.cell(pos), Cell::Marked);
+        self.painted.remove(pos);
+        self.empty_cell_count += 1;
+    }
+
+    pub fn undo_drill(&mut self, pos: Pos) {
+        assert_eq!(self.cell(pos), Cell::Marked);
+        self.painted.remove(pos);
+        self.walls.insert(pos);
+    }
 }
 
 #[cfg(test)]
 mod test {
     use super::*;
+    use proptest::prelude::*;
+
+    // Cells whose centers are inside the tour, by counting the vertical edges on their right.
+    fn rasterize(tour: &[Pos], max_x: i32, max_y: i32) -> BitGrid {
+        let mut inside = BitGrid::new(max_x, max_y);
+        for y in 0..max_y {
+            for x in 0..max_x {
+                let crossings = (0..tour.len())
+                    .filter(|i| {
+                        let (a, b) = (tour[*i], tour[(*i + 1) % tour.len()]);
+                        a.x == b.x && a.x > x && a.y.min(b.y) <= y && y < a.y.max(b.y)
+                    })
+                    .count();
+                if crossings % 2 == 1 {
+                    inside.insert(Pos::new(x, y));
+                }
+            }
+        }
+        inside
+    }
+
+    fn cells(grid: &BitGrid) -> Vec<Pos> {
+        (0..grid.width())
+            .flat_map(|x| (0..grid.height()).map(move |y| Pos::new(x, y)))
+            .filter(|pos| grid.contains(*pos))
+            .collect()
+    }
 
     #[test]
-    fn pase_tour_test() {
+    fn normalize_tour_test() -> Result<()> {
+        let tour = |s: &str| Task::parse_tour(s).unwrap();
+        let square = tour("(0,0),(2,0),(2,2),(0,2)");
+        // Clockwise, with a repeated vertex and vertices in the middle of edges.
         assert_eq!(
-            Task::parse_tour("(0,0),(10,0),(10,10),(0,10)"),
+            Task::normalize_tour(&tour("(0,0),(0,1),(0,2),(2,2),(2,2),(2,0),(1,0),(0,0)"))?,
+            tour("(2,0),(2,2),(0,2),(0,0)")
+        );
+        assert_eq!(Task::normalize_tour(&square)?, square);
+        assert!(Task::normalize_tour(&tour("(0,0),(2,0),(2,2)")).is_err());
+        assert!(Task::normalize_tour(&tour("(0,0),(2,0),(1,0),(1,2),(0,2)")).is_err());
+        assert!(
+            Task::normalize_tour(&tour("(0,1),(3,1),(3,3),(2,3),(2,0),(1,0),(1,2),(0,2)")).is_err()
+        );
+
+        // A clockwise map, with an obstacle along its left edge.
+        let map = Map::new(Task::parse(
+            0,
+            "(0,0),(0,4),(4,4),(4,0)#(2,2)#(0,0),(1,0),(1,4),(0,4)#",
+        )?);
+        assert_eq!(map.empty_cell_count, 12);
+        assert!(map.is_wall(Pos::new(0, 3)));
+        assert!(!map.is_wall(Pos::new(1, 3)));
+
+        // Built by hand, the same checks apply.
+        let task = Task::new(
+            0,
+            tour("(0,0),(0,2),(2,2),(2,0)"),
+            Pos::new(1, 1),
+            vec![],
+            vec![],
+        )?;
+        assert_eq!(task.map(), &tour("(2,0),(2,2),(0,2),(0,0)")[..]);
+        let crossing = tour("(0,1),(3,1),(3,3),(2,3),(2,0),(1,0),(1,2),(0,2)");
+        assert!(Task::new(0, crossing, Pos::new(0, 1), vec![], vec![]).is_err());
+        assert!(Task::new(0, square, Pos::new(2, 2), vec![], vec![]).is_err());
+        Ok(())
+    }
+
+    // A closed tour with axis-aligned edges through the given coordinates, which may double back
+    // or cross itself.
+    fn rectilinear(xs: &[i32], ys: &[i32]) -> String {
+        let mut tour = vec![];
+        for (x, y) in xs.iter().zip(ys) {
+            if let Some((_, last_y)) = tour.last().cloned() {
+                tour.push((*x, last_y));
+            }
+            tour.push((*x, *y));
+        }
+        tour.push((xs[0], *ys.last().unwrap()));
+        tour.iter()
+            .map(|(x, y)| format!("({},{})", x, y))
+            .collect::<Vec<_>>()
+            .join(",")
+    }
+
+    proptest! {
+        #[test]
+        fn parse_fuzz_test(s in "[(),;#0-9BFLXRC-]{0,60}|\\PC*") {
+            let _ = Task::parse_tour(&s);
+            let _ = Task::parse_boosters(&s);
+            if let Ok(task) = Task::parse(0, &s) {
+                Map::new(task);
+            }
+        }
+
+        // Accepted tours are filled the same as the rasterizer does.
+        #[test]
+        fn parse_tour_fuzz_test(
+            xs in prop::collection::vec(0..12i32, 2..8),
+            ys in prop::collection::vec(0..12i32, 2..8),
+            obstacle in prop::collection::vec(0..12i32, 2..4),
+        ) {
+            let map = rectilinear(&xs, &ys);
+            let s = format!("{}#(0,0)#{}#", map, rectilinear(&obstacle, &obstacle));
+            if let Ok(task) = Task::parse(0, &s) {
+                Map::new(task);
+            }
+            if let Ok(task) = Task::parse(0, &format!("{}#(0,0)##", map)) {
+                let inside = rasterize(&task.map, task.max_x(), task.max_y());
+                prop_assert_eq!(Map::new(task).empty_cell_count, inside.count());
+            }
+        }
+
+        #[test]
+        fn fill_tour_test(
+            size in 3..24i32,
+            erosion in 0..200usize,
+            obstacles in 0..5usize,
+            seed: u64,
+            clockwise: bool,
+        ) {
+            let options = crate::generate::GenerateOptions {
+                size,
+                erosion,
+                obstacles,
+                boosters: 0,
+                seed,
+            };
+            let mut task = crate::generate::generate(0, &options).unwrap();
+            if clockwise {
+                task.map.reverse();
+                for obstacle in &mut task.obstacles {
+                    obstacle.reverse();
+                }
+            }
+            let (max_x, max_y) = (task.max_x(), task.max_y());
+
+            let inside = rasterize(&task.map, max_x, max_y);
+            prop_assert_eq!(cells(&Map::fill_tour(&task.map, max_x, max_y)), cells(&inside));
+            let mut empty = inside.count();
+            for obstacle in &task.obstacles {
+                let hole = rasterize(obstacle, max_x, max_y);
+                prop_assert_eq!(cells(&Map::fill_tour(obstacle, max_x, max_y)), cells(&hole));
+                empty -= hole.count();
+            }
+            prop_assert_eq!(Map::new(task).empty_cell_count, empty);
+        }
+    }
+
+    #[test]
+    fn pase_tour_test() -> Result<()> {
+        assert_eq!(
+            Task::parse_tour("(0,0),(10,0),(10,10),(0,10)")?,
             vec![
                 Pos::new(0, 0),
                 Pos::new(10, 0),
@@ -448,36 +839,45 @@ mod test {
                 Pos::new(0, 10)
             ]
         );
+        assert!(Task::parse_tour("(0,0),(10,0").is_err());
+        assert!(Task::parse_tour("(0,0,1)").is_err());
+        Ok(())
     }
 
     #[test]
-    fn pase_pos_test() {
-        assert_eq!(Task::parse_pos("(0,1)"), Pos::new(0, 1),);
+    fn pase_pos_test() -> Result<()> {
+        assert_eq!(Task::parse_pos("(0,1)")?, Pos::new(0, 1),);
+        assert!(Task::parse_pos("(").is_err());
+        Ok(())
     }
 
     #[test]
-    fn pase_obstables_test() {
+    fn pase_obstables_test() -> Result<()> {
         assert_eq!(
-            Task::parse_obstacles("(4,2),(6,2);(0,1)"),
+            Task::parse_obstacles("(4,2),(6,2);(0,1)")?,
             vec![vec![Pos::new(4, 2), Pos::new(6, 2)], vec![Pos::new(0, 1)]]
         );
+        Ok(())
     }
 
     #[test]
-    fn pase_booster_test() {
+    fn pase_booster_test() -> Result<()> {
         assert_eq!(
-            Task::parse_booster("F(4,2)"),
+            Task::parse_booster("F(4,2)")?,
             Booster {
                 pos: Pos::new(4, 2),
                 kind: BoosterKind::FastWheels,
             }
         );
+        assert!(Task::parse_booster("Y(4,2)").is_err());
+        assert!(Task::parse_booster("").is_err());
+        Ok(())
     }
 
     #[test]
-    fn pase_boosters_test() {
+    fn pase_boosters_test() -> Result<()> {
         assert_eq!(
-            Task::parse_boosters("F(4,2);B(0,1)"),
+            Task::parse_boosters("F(4,2);B(0,1)")?,
             vec![
                 Booster {
                     pos: Pos::new(4, 2),
@@ -490,7 +890,8 @@ mod test {
             ]
         );
 
-        assert_eq!(Task::parse_boosters(""), vec![]);
+        assert_eq!(Task::parse_boosters("")?, vec![]);
+        Ok(())
     }
 
     #[test]
@@ -509,6 +910,19 @@ mod test {
         Ok(())
     }
 
+    #[test]
+    fn format_task_test() -> Result<()> {
+        for id in 1..=3 {
+            let desc = std::fs::read_to_string(format!(
+                "{}/contest/problem/prob-{:03}.desc",
+                env!("CARGO_MANIFEST_DIR"),
+                id
+            ))?;
+            assert_eq!(Task::parse(id, &desc)?.to_string(), desc.trim());
+        }
+        Ok(())
+    }
+
     #[test]
     fn map_test() -> Result<()> {
         let task = Task::read_with_id(2)?;
@@ -562,5 +976,4 @@ O.....##########....#......#...#...#######
         );
         Ok(())
     }
-
 }
@@ -0,0 +1,56 @@
+use crate::prelude::*;
+use crate::task::*;
+
+/// One action of a bot, as a line of a .trace.jsonl file.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct TraceEvent {
+    pub turn: usize,
+    pub bot: usize,
+    // The pose after the action.
+    pub x: i32,
+    pub y: i32,
+    pub angle: u32,
+    pub action: String,
+    // The number of cells newly painted by the action.
+    pub painted: usize,
+    pub plan: Option<String>,
+    pub fast_wheel_timer: usize,
+    pub drill_timer: usize,
+    // Cells left to paint after the action.
+    pub empty: usize,
+    // Boosters picked up by the action.
+    pub picked: Vec<String>,
+}
+
+// Records the actions taken by System::step, for runs which ask for it.
+#[derive(Debug, Clone, Default)]
+pub struct Trace {
+    events: Vec<TraceEvent>,
+}
+
+impl Trace {
+    // Fills in the boosters picked up by the action.
+    pub(crate) fn record(&mut self, mut event: TraceEvent, picked: &[(Pos, BoosterKind)]) {
+        event.picked = picked.iter().map(|(_, kind)| kind.to_string()).collect();
+        self.events.push(event);
+    }
+
+    pub(crate) fn undo(&mut self) {
+        self.events.pop().unwrap();
+    }
+
+    pub fn to_json_lines(&self) -> String {
+        self.events
+            .iter()
+            .map(|event| serde_json::to_string(event).unwrap() + "\n")
+            .collect()
+    }
+}
+
+/// Parses a .trace.jsonl file.
+pub fn parse_json_lines(s: &str) -> Result<Vec<TraceEvent>> {
+    s.lines()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| Ok(serde_json::from_str(line)?))
+        .collect()
+}
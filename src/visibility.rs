@@ -0,0 +1,310 @@
+use crate::grid::*;
+use crate::system::*;
+use crate::task::*;
+
+const ANGLES: [Angle; 4] = [Angle::A0, Angle::A90, Angle::A180, Angle::A270];
+
+// A manipulator layout turned to one angle.
+#[derive(Debug, Clone, PartialEq, Default)]
+struct AngleView {
+    // The cell each manipulator paints, and the cells on the line to it which must not be walls.
+    manipulators: Vec<(PosDiff, Vec<PosDiff>)>,
+    // Cells next to the painted ones, but not painted themselves.
+    adjacent: Vec<PosDiff>,
+    // Every cell which decides what is painted, so a change of a cell affects the poses this far
+    // from it.
+    footprint: Vec<PosDiff>,
+}
+
+// What a manipulator layout paints at each angle, turned once instead of at every pose of the
+// search.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct Visibility {
+    layout: Vec<PosDiff>,
+    angles: Vec<AngleView>,
+}
+
+impl Visibility {
+    pub(crate) fn new(manipulators: &[Manipulator]) -> Visibility {
+        let angles = ANGLES
+            .iter()
+            .map(|angle| {
+                let mut view = AngleView::default();
+                for manipulator in manipulators {
+                    let target = manipulator.posdiff.turn(*angle);
+                    let line = manipulator
+                        .reachable_cell
+                        .iter()
+                        .map(|reach| reach.turn(*angle))
+                        .collect::<Vec<_>>();
+                    for diff in line.iter().chain(std::iter::once(&target)) {
+                        if !view.footprint.contains(diff) {
+                            view.footprint.push(*diff);
+                        }
+                    }
+                    view.manipulators.push((target, line));
+                }
+                for (target, _) in &view.manipulators {
+                    for adj in PosDiff::gen_all_diff() {
+                        let diff = PosDiff::new(target.dx + adj.dx, target.dy + adj.dy);
+                        if !view.adjacent.contains(&diff)
+                            && view.manipulators.iter().all(|(target, _)| *target != diff)
+                        {
+                            view.adjacent.push(diff);
+                        }
+                    }
+                }
+                view
+            })
+            .collect();
+        Visibility {
+            layout: manipulators
+                .iter()
+                .map(|manipulator| manipulator.posdiff)
+                .collect(),
+            angles,
+        }
+    }
+
+    fn is_for(&self, manipulators: &[Manipulator]) -> bool {
+        self.layout.len() == manipulators.len()
+            && self
+                .layout
+                .iter()
+                .zip(manipulators)
+                .all(|(posdiff, manipulator)| *posdiff == manipulator.posdiff)
+    }
+
+    // The cells which would be painted at the pose.
+    pub(crate) fn marks<'a>(
+        &'a self,
+        pos_angle: PosAngle,
+        map: &'a Map,
+    ) -> impl Iterator<Item = Pos> + 'a {
+        let pos = pos_angle.pos;
+        self.angles[pos_angle.angle as usize]
+            .manipulators
+            .iter()
+            .filter(move |(target, line)| {
+                map.is_empty(pos + *target) && line.iter().all(|reach| !map.is_wall(pos + *reach))
+            })
+            .map(move |(target, _)| pos + *target)
+    }
+
+    // Empty cells next to the manipulators, which are worth painting next.
+    pub(crate) fn adjacent_empty_cells(&self, pos_angle: PosAngle, map: &Map) -> usize {
+        self.angles[pos_angle.angle as usize]
+            .adjacent
+            .iter()
+            .filter(|diff| map.is_empty(pos_angle.pos + **diff))
+            .count()
+    }
+}
+
+// How many cells each pose would paint with one manipulator layout. Painting or drilling a cell
+// only changes the poses within the footprint of the layout around it, so only those are
+// recomputed.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct GainMap {
+    visibility: Visibility,
+    gains: Grid<[u8; 4]>,
+}
+
+impl GainMap {
+    fn new(visibility: Visibility, map: &Map) -> GainMap {
+        let mut gains = Grid::new(map.max_x, map.max_y, [0; 4]);
+        for pos in gains.positions().collect::<Vec<_>>() {
+            for angle in &ANGLES {
+                gains[pos][*angle as usize] =
+                    visibility.marks(PosAngle::new(pos, *angle), map).count() as u8;
+            }
+        }
+        GainMap { visibility, gains }
+    }
+
+    pub(crate) fn visibility(&self) -> &Visibility {
+        &self.visibility
+    }
+
+    pub(crate) fn gain(&self, pos_angle: PosAngle) -> usize {
+        self.gains[pos_angle.pos][pos_angle.angle as usize] as usize
+    }
+
+    fn update(&mut self, changed: &[Pos], map: &Map) {
+        for cell in changed {
+            for angle in &ANGLES {
+                for diff in &self.visibility.angles[*angle as usize].footprint {
+                    let pos = Pos::new(cell.x - diff.dx, cell.y - diff.dy);
+                    if self.gains.is_in_range(pos) {
+                        self.gains[pos][*angle as usize] =
+                            self.visibility
+                                .marks(PosAngle::new(pos, *angle), map)
+                                .count() as u8;
+                    }
+                }
+            }
+        }
+    }
+}
+
+// A gain map for each manipulator layout the bots have.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub(crate) struct GainMaps {
+    maps: Vec<GainMap>,
+}
+
+impl GainMaps {
+    pub(crate) fn new(bots: &[Bot], map: &Map) -> GainMaps {
+        let mut gain_maps = GainMaps::default();
+        gain_maps.sync(bots, map);
+        gain_maps
+    }
+
+    // Builds the maps of new layouts, and drops those no bot has any more.
+    pub(crate) fn sync(&mut self, bots: &[Bot], map: &Map) {
+        self.maps.retain(|gain_map| {
+            bots.iter()
+                .any(|bot| gain_map.visibility.is_for(&bot.manipulators))
+        });
+        for bot in bots {
+            if self.get(&bot.manipulators).is_none() {
+                self.maps
+                    .push(GainMap::new(Visibility::new(&bot.manipulators), map));
+            }
+        }
+    }
+
+    // To be called with the cells painted, drilled or restored by undo.
+    pub(crate) fn update(&mut self, changed: &[Pos], map: &Map) {
+        if changed.is_empty() {
+            return;
+        }
+        for gain_map in &mut self.maps {
+            gain_map.update(changed, map);
+        }
+    }
+
+    pub(crate) fn get(&self, manipulators: &[Manipulator]) -> Option<&GainMap> {
+        self.maps
+            .iter()
+            .find(|gain_map| gain_map.visibility.is_for(manipulators))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::bench;
+    use crate::prelude::*;
+    use crate::replay::*;
+
+    #[test]
+    fn visibility_test() -> Result<()> {
+        let system = bench::half_painted(bench::task("small"));
+        let map = &system.map;
+        let mut manipulators = Bot::new(Pos::new(0, 0)).manipulators;
+        manipulators.push(Manipulator::new(PosDiff::new(1, 2)));
+        manipulators.push(Manipulator::new(PosDiff::new(-3, 1)));
+        let visibility = Visibility::new(&manipulators);
+        for y in 0..map.max_y {
+            for x in 0..map.max_x {
+                for angle in &ANGLES {
+                    let pos_angle = PosAngle::new(Pos::new(x, y), *angle);
+                    let marks = manipulators
+                        .iter()
+                        .filter(|manipulator| manipulator.can_mark(pos_angle, map))
+                        .map(|manipulator| pos_angle + manipulator.posdiff)
+                        .collect::<Vec<_>>();
+                    assert_eq!(visibility.marks(pos_angle, map).collect::<Vec<_>>(), marks);
+
+                    let targets = manipulators
+                        .iter()
+                        .map(|manipulator| pos_angle + manipulator.posdiff)
+                        .collect::<HashSet<_>>();
+                    let adjacent = targets
+                        .iter()
+                        .flat_map(|pos| {
+                            PosDiff::gen_all_diff().iter().map(move |diff| *pos + *diff)
+                        })
+                        .filter(|pos| map.is_empty(*pos) && !targets.contains(pos))
+                        .collect::<HashSet<_>>();
+                    assert_eq!(
+                        visibility.adjacent_empty_cells(pos_angle, map),
+                        adjacent.len()
+                    );
+                }
+            }
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn gain_maps_test() -> Result<()> {
+        // Replay a solution which extends manipulators and clones, checking the gain maps
+        // against fresh ones at every step and after every undo.
+        let mut system = System::from_task(bench::task("small"));
+        system.solve()?;
+        let programs = parse_solution(&system.dump_record())?;
+        assert!(programs.len() > 1);
+        assert!(programs[0]
+            .iter()
+            .any(|action| matches!(action, Action::ExtendManipulator(_))));
+
+        let mut system = System::from_task(bench::task("small"));
+        let mut deltas = vec![];
+        let mut next = vec![0; programs.len()];
+        while (0..programs.len()).any(|i| next[i] < programs[i].len()) {
+            for i in 0..system.bots.len() {
+                let action = match programs[i].get(next[i]) {
+                    Some(Action::ExtendManipulator(posdiff)) => Action::ExtendManipulator(
+                        posdiff.turn(system.bots[i].pos_angle.angle.inverse()),
+                    ),
+                    Some(action) => *action,
+                    None => continue,
+                };
+                next[i] += 1;
+                deltas.push(system.step(i, action));
+                assert_eq!(system.gain_maps, GainMaps::new(&system.bots, &system.map));
+            }
+        }
+        assert!(system.is_solved());
+        while let Some(delta) = deltas.pop() {
+            system.undo(delta);
+            assert_eq!(system.gain_maps, GainMaps::new(&system.bots, &system.map));
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn gain_maps_drill_test() -> Result<()> {
+        // Fast wheels paint on the way and drilled walls open up poses behind them.
+        use Action::*;
+        let task = Task::parse(
+            0,
+            "(0,0),(10,0),(10,3),(0,3)#(0,1)#(4,0),(6,0),(6,3),(4,3)#",
+        )?;
+        let mut system = System::from_task(task);
+        system.inventory.add(BoosterKind::FastWheels, 0);
+        system.inventory.add(BoosterKind::Drill, 0);
+        let mut deltas = vec![];
+        for action in &[
+            AttachFastWheels,
+            AttachDrill,
+            MoveRight,
+            MoveRight,
+            TurnClockWise,
+            MoveRight,
+            MoveUp,
+            MoveLeft,
+        ] {
+            deltas.push(system.step(0, *action));
+            assert_eq!(system.gain_maps, GainMaps::new(&system.bots, &system.map));
+        }
+        assert!(deltas.iter().any(|delta| !delta.drilled.is_empty()));
+        while let Some(delta) = deltas.pop() {
+            system.undo(delta);
+            assert_eq!(system.gain_maps, GainMaps::new(&system.bots, &system.map));
+        }
+        Ok(())
+    }
+}